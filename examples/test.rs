@@ -1,10 +1,6 @@
-use bevy::tasks::futures_lite::StreamExt;
 use bevy_nannou_pixelmap::*;
 use nannou::prelude::*;
-use sacn::packet::ACN_SDT_MULTICAST_PORT;
-use sacn::source::SacnSource;
-use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
-use artnet_protocol::{ArtCommand, Output};
+use std::net::Ipv4Addr;
 
 fn main() {
     nannou::app(model)
@@ -17,7 +13,6 @@ struct Model {
     window: Entity,
     camera: Entity,
     pixelmap: Entity,
-    socket: UdpSocket,
 }
 
 fn model(app: &App) -> Model {
@@ -42,27 +37,13 @@ fn model(app: &App) -> Model {
         .count(12)
         .x_y(100.0, 100.0)
         .w_h(200.0, 20.0)
-        .build(|evt, model: &mut Model| {
-            let broadcast_addr = ("192.168.2.4", 6454).to_socket_addrs().unwrap().next().unwrap();
-            let command = ArtCommand::Output(Output {
-                data: evt.event().iter().map(|x|(*x * 255.0) as u8).collect::<Vec<u8>>().into(),
-                ..Output::default()
-            });
-            let bytes = command.write_to_buffer().unwrap();
-            model
-                .socket
-                .send_to(&bytes, broadcast_addr).unwrap();
-        });
-
-    let socket = UdpSocket::bind(("0.0.0.0", 6454)).unwrap();
-    socket.set_broadcast(true).unwrap();
-
+        .artnet(ArtNetOutput::new(Ipv4Addr::new(192, 168, 2, 4)))
+        .build(|_evt, _model: &mut Model| {});
 
     Model {
         camera,
         window,
         pixelmap,
-        socket,
     }
 }
 
@@ -101,5 +82,4 @@ fn view(app: &App, model: &Model) {
         .x_y(x2, y2)
         .w_h(100.0, 100.0)
         .emissive(color_linear_rgb);
-
 }
@@ -1,5 +1,10 @@
-use crate::{LedArea, LedBundle, ReceivedData};
-use bevy::prelude::{Entity, ResMut, Trigger, Vec2};
+use crate::{
+    ArtNetOutput, ColorCalibration, CubeLut, DdpOutput, Dimmer, EnttecOutput, FixtureType, LedArea,
+    LedBundle, LedLut, LedMatrix, LedPath, LedPointCloud, LedRing, LedTonemapping, LedXModel,
+    OpcOutput, OutputDepth, PixelFormat, PowerModel, ReceivedData, SacnOutput, TemporalDither,
+    TransferCurve, WhiteBalance, WhiteExtraction, WledOutput,
+};
+use bevy::prelude::{Bundle, Entity, EntityWorldMut, Handle, ResMut, Trigger, Vec2};
 use bevy::render::view::RenderLayers;
 use bevy::utils::default;
 use nannou::app::ModelHolder;
@@ -47,7 +52,126 @@ pub trait SetPixelmap: Sized {
         })
     }
 
+    /// Lay the pixelmap out as a grid of LEDs rather than a single strip.
+    fn matrix(self, matrix: LedMatrix) -> Self {
+        self.count(matrix.count()).insert(matrix)
+    }
+
+    /// Lay the pixelmap's LEDs out evenly along a path rather than a straight strip.
+    fn path(self, path: LedPath) -> Self {
+        self.insert(path)
+    }
+
+    /// Lay the pixelmap's LEDs out on a ring, arc or spiral within its area.
+    fn ring(self, ring: LedRing) -> Self {
+        self.insert(ring)
+    }
+
+    /// Lay the pixelmap's LEDs out at the points of a point cloud.
+    fn point_cloud(self, point_cloud: LedPointCloud) -> Self {
+        self.insert(point_cloud)
+    }
+
+    /// The channels sent for each pixel and their order.
+    fn pixel_format(self, format: PixelFormat) -> Self {
+        self.insert(format)
+    }
+
+    /// How the white channel is derived for formats that have one.
+    fn white_extraction(self, white_extraction: WhiteExtraction) -> Self {
+        self.insert(white_extraction)
+    }
+
+    /// The colour temperatures of the white LEDs of RGBWW pixels, in kelvin.
+    fn white_balance(self, cool: f32, warm: f32) -> Self {
+        self.insert(WhiteBalance::new(cool, warm))
+    }
+
+    /// The curve that encodes linear light into the values the LED driver expects.
+    fn transfer_curve(self, transfer_curve: TransferCurve) -> Self {
+        self.insert(transfer_curve)
+    }
+
+    /// Encode for a driver that decodes with `gamma`, such as 2.2, by raising each channel to
+    /// the power of `1 / gamma`. Gammas that aren't positive and finite send linear light.
+    fn gamma(self, gamma: f32) -> Self {
+        self.transfer_curve(TransferCurve::Gamma(gamma))
+    }
+
+    /// The resolution of each channel, 16-bit sends a coarse/fine pair per channel.
+    fn output_depth(self, depth: OutputDepth) -> Self {
+        self.insert(depth)
+    }
+
+    /// Carry the quantization error of 8-bit output across frames for smoother fades.
+    fn temporal_dither(self) -> Self {
+        self.insert(TemporalDither::default())
+    }
+
+    /// Tone map the sampled HDR colour for the LEDs, independent of the on-screen tonemapping.
+    fn tonemapping(self, tonemapping: LedTonemapping) -> Self {
+        self.insert(tonemapping)
+    }
+
+    /// The pixelmap's intensity, from 0 to 1.
+    fn dimmer(self, intensity: f32) -> Self {
+        self.insert(Dimmer(intensity))
+    }
+
+    /// Grade the pixelmap's output with a 3D LUT.
+    fn lut(self, lut: Handle<CubeLut>) -> Self {
+        self.insert(LedLut(lut))
+    }
+
+    /// Correct the colour of the pixelmap's fixtures.
+    fn calibration(self, calibration: ColorCalibration) -> Self {
+        self.insert(calibration)
+    }
+
+    /// Calibrate the pixelmap with the profile registered for its fixture type.
+    fn fixture_type(self, fixture_type: impl Into<String>) -> Self {
+        self.insert(FixtureType::new(fixture_type))
+    }
+
+    /// Estimate the pixelmap's current draw and dim it to stay within its power budget.
+    fn power_model(self, power_model: PowerModel) -> Self {
+        self.insert(power_model)
+    }
+
+    /// Send the pixelmap's data to an Art-Net node.
+    fn artnet(self, output: ArtNetOutput) -> Self {
+        self.insert(output)
+    }
+
+    /// Send the pixelmap's data as E1.31 (sACN).
+    fn sacn(self, output: SacnOutput) -> Self {
+        self.insert(output)
+    }
+
+    /// Send the pixelmap's data to a DDP device.
+    fn ddp(self, output: DdpOutput) -> Self {
+        self.insert(output)
+    }
+
+    /// Send the pixelmap's data to an Open Pixel Control server.
+    fn opc(self, output: OpcOutput) -> Self {
+        self.insert(output)
+    }
+
+    /// Send the pixelmap's data to a WLED node.
+    fn wled(self, output: WledOutput) -> Self {
+        self.insert(output)
+    }
+
+    /// Send the pixelmap's data to an Enttec DMX USB Pro style widget.
+    fn enttec(self, output: EnttecOutput) -> Self {
+        self.insert(output)
+    }
+
     fn map_leds(self, f: impl FnOnce(LedBundle) -> LedBundle) -> Self;
+
+    /// Insert additional components, such as outputs, on the pixelmap entity.
+    fn insert(self, bundle: impl Bundle) -> Self;
 }
 
 pub struct Builder<'a, 'w, M>
@@ -56,6 +180,7 @@ where
 {
     app: &'a nannou::App<'w>,
     leds: LedBundle,
+    components: Vec<Box<dyn FnOnce(&mut EntityWorldMut)>>,
     _marker: std::marker::PhantomData<M>,
 }
 
@@ -67,6 +192,7 @@ where
         Self {
             app,
             leds: Default::default(),
+            components: Vec::new(),
             _marker: Default::default(),
         }
     }
@@ -76,8 +202,11 @@ where
         mut callback: impl FnMut(Trigger<ReceivedData>, &mut M) + Send + Sync + 'static,
     ) -> Entity {
         let world = unsafe { self.app.unsafe_world_mut() };
-        world
-            .spawn((self.leds, RenderLayers::layer(32)))
+        let mut entity = world.spawn((self.leds, RenderLayers::layer(32)));
+        for insert in self.components {
+            insert(&mut entity);
+        }
+        entity
             .observe(
                 move |trigger: Trigger<ReceivedData>, mut model: ResMut<ModelHolder<M>>| {
                     callback(trigger, &mut model.0);
@@ -97,6 +226,13 @@ where
             ..self
         }
     }
+
+    fn insert(mut self, bundle: impl Bundle) -> Self {
+        self.components.push(Box::new(move |entity| {
+            entity.insert(bundle);
+        }));
+        self
+    }
 }
 
 pub struct PixelmapArea<'a, 'w> {
@@ -124,6 +260,12 @@ impl SetPixelmap for PixelmapArea<'_, '_> {
         world.entity_mut(self.entity).insert(bundle);
         self
     }
+
+    fn insert(self, bundle: impl Bundle) -> Self {
+        let world = unsafe { self.app.unsafe_world_mut() };
+        world.entity_mut(self.entity).insert(bundle);
+        self
+    }
 }
 
 pub trait AppPixelmapExt<'w> {
@@ -131,6 +273,10 @@ pub trait AppPixelmapExt<'w> {
     fn new_pixelmap<'a, M>(&'a self) -> Builder<'a, 'w, M>
     where
         M: Send + Sync + 'static;
+
+    /// Spawn an xLights model, with a `LedArea` for each of its strings. `outputs` are patched
+    /// at the model's first channel, and each string's copy moves on to its own start channel.
+    fn new_xmodel(&self, model: LedXModel, outputs: impl Bundle) -> Entity;
 }
 
 impl<'w> AppPixelmapExt<'w> for nannou::App<'w> {
@@ -140,4 +286,9 @@ impl<'w> AppPixelmapExt<'w> for nannou::App<'w> {
     {
         Builder::new(self)
     }
+
+    fn new_xmodel(&self, model: LedXModel, outputs: impl Bundle) -> Entity {
+        let world = unsafe { self.unsafe_world_mut() };
+        world.spawn((model, outputs)).id()
+    }
 }
@@ -0,0 +1,487 @@
+use crate::output::{encode_output_data, DmxPatch, OutputData, PixelStraddle};
+use crate::socket::bind_udp_socket;
+use artnet_protocol::{ArtCommand, Output, Poll, PollReply};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::Duration;
+
+/// The UDP port used by Art-Net.
+pub const ARTNET_PORT: u16 = 6454;
+
+/// An ArtSync packet: the Art-Net ID, the OpSync op-code, protocol version 14 and two aux bytes.
+const ART_SYNC_PACKET: [u8; 14] = [
+    b'A', b'r', b't', b'-', b'N', b'e', b't', 0, 0x00, 0x52, 0, 14, 0, 0,
+];
+
+pub struct ArtNetPlugin;
+
+impl Plugin for ArtNetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ArtNetUniverses>()
+            .init_resource::<DiscoveredArtNetNodes>()
+            .observe(patch_artnet_output)
+            .add_systems(
+                PreUpdate,
+                (
+                    bind_artnet_socket.run_if(not(resource_exists::<ArtNetSocket>)),
+                    (
+                        receive_artnet_replies,
+                        poll_artnet_nodes.run_if(resource_exists::<ArtPollSettings>),
+                    )
+                        .run_if(resource_exists::<ArtNetSocket>),
+                )
+                    .chain(),
+            )
+            .add_systems(
+                First,
+                send_artnet_universes
+                    .after(encode_output_data)
+                    .run_if(resource_exists::<ArtNetSocket>),
+            );
+    }
+}
+
+/// The socket used to send Art-Net packets. Insert this resource to bind to a specific
+/// interface, otherwise all interfaces are bound on the Art-Net port once there is an
+/// `ArtNetOutput` or `ArtPollSettings`.
+#[derive(Resource, Deref)]
+pub struct ArtNetSocket(UdpSocket);
+
+impl ArtNetSocket {
+    /// Bind to `addr`, sharing the port with any other Art-Net software on the machine.
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        bind_udp_socket(addr).map(Self)
+    }
+}
+
+/// Periodically broadcast ArtPoll to discover nodes. Insert this resource to enable discovery.
+#[derive(Resource, Clone, Debug)]
+pub struct ArtPollSettings {
+    /// Where to send polls, usually the broadcast address of the interface the `ArtNetSocket` is
+    /// bound to.
+    pub destination: SocketAddr,
+    /// How often to poll.
+    pub interval: Duration,
+    /// How long a node may go without replying before it is forgotten.
+    pub timeout: Duration,
+}
+
+impl Default for ArtPollSettings {
+    fn default() -> Self {
+        Self {
+            destination: SocketAddr::new(Ipv4Addr::BROADCAST.into(), ARTNET_PORT),
+            interval: Duration::from_secs(3),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// A node that replied to an ArtPoll.
+#[derive(Clone, Debug)]
+pub struct ArtNetNode {
+    pub address: Ipv4Addr,
+    pub short_name: String,
+    pub long_name: String,
+    /// The port-addresses of the node's output ports.
+    pub port_addresses: Vec<u16>,
+    pub mac: [u8; 6],
+    pub firmware: u16,
+    /// Distinguishes the pages of a node with more than four ports.
+    pub bind_index: u8,
+    last_seen: Duration,
+}
+
+/// The nodes that have replied to an ArtPoll, keyed by address and bind index.
+#[derive(Resource, Default, Debug)]
+pub struct DiscoveredArtNetNodes(HashMap<(Ipv4Addr, u8), ArtNetNode>);
+
+impl DiscoveredArtNetNodes {
+    pub fn iter(&self) -> impl Iterator<Item = &ArtNetNode> {
+        self.0.values()
+    }
+
+    /// Find a node by its short or long name.
+    pub fn find(&self, name: &str) -> Option<&ArtNetNode> {
+        self.iter()
+            .find(|node| node.short_name == name || node.long_name == name)
+    }
+}
+
+/// Sends a `LedArea`'s pixel data to an Art-Net node.
+#[derive(Component, Clone, Debug)]
+pub struct ArtNetOutput {
+    /// The node to send to, either a unicast or a broadcast address.
+    pub destination: SocketAddr,
+    /// The name of a discovered node to send to instead of `destination`. Nothing is sent until
+    /// the node has replied to an ArtPoll.
+    pub node: Option<String>,
+    /// The 7-bit net of the port-address.
+    pub net: u8,
+    /// The 4-bit subnet of the port-address.
+    pub subnet: u8,
+    /// The 4-bit universe of the port-address.
+    pub universe: u8,
+    /// The physical input port the data originates from.
+    pub physical: u8,
+    /// Where the area's channels start, continuing into consecutive port-addresses.
+    pub patch: DmxPatch,
+    /// Send an ArtSync to the destination after each frame, so that nodes which support it
+    /// latch all of their universes simultaneously.
+    pub sync: bool,
+}
+
+impl ArtNetOutput {
+    pub fn new(ip: impl Into<IpAddr>) -> Self {
+        Self {
+            destination: SocketAddr::new(ip.into(), ARTNET_PORT),
+            node: None,
+            net: 0,
+            subnet: 0,
+            universe: 0,
+            physical: 0,
+            patch: DmxPatch::default(),
+            sync: false,
+        }
+    }
+
+    pub fn broadcast() -> Self {
+        Self::new(Ipv4Addr::BROADCAST)
+    }
+
+    /// Send to the discovered node with the given short or long name.
+    pub fn node(name: impl Into<String>) -> Self {
+        Self {
+            node: Some(name.into()),
+            ..Self::new(Ipv4Addr::UNSPECIFIED)
+        }
+    }
+
+    pub fn net(mut self, net: u8) -> Self {
+        self.net = net;
+        self
+    }
+
+    pub fn subnet(mut self, subnet: u8) -> Self {
+        self.subnet = subnet;
+        self
+    }
+
+    pub fn universe(mut self, universe: u8) -> Self {
+        self.universe = universe;
+        self
+    }
+
+    pub fn physical(mut self, physical: u8) -> Self {
+        self.physical = physical;
+        self
+    }
+
+    pub fn start_channel(mut self, start_channel: u16) -> Self {
+        self.patch.start_channel = start_channel;
+        self
+    }
+
+    pub fn straddle(mut self, straddle: PixelStraddle) -> Self {
+        self.patch.straddle = straddle;
+        self
+    }
+
+    pub fn sync(mut self, sync: bool) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    /// The 15-bit port-address composed of the net, subnet and universe.
+    pub fn port_address(&self) -> u16 {
+        ((self.net as u16 & 0x7f) << 8)
+            | ((self.subnet as u16 & 0xf) << 4)
+            | (self.universe as u16 & 0xf)
+    }
+}
+
+#[derive(Default)]
+struct ArtNetUniverse {
+    physical: u8,
+    sequence: u8,
+    sync: bool,
+    data: Vec<u8>,
+    dirty: bool,
+}
+
+/// The universes patched this frame, keyed by destination and port-address.
+#[derive(Resource, Deref, DerefMut, Default)]
+struct ArtNetUniverses(HashMap<(SocketAddr, u16), ArtNetUniverse>);
+
+fn patch_artnet_output(
+    trigger: Trigger<OutputData>,
+    outputs_q: Query<&ArtNetOutput>,
+    nodes: Res<DiscoveredArtNetNodes>,
+    mut universes: ResMut<ArtNetUniverses>,
+) {
+    let Ok(output) = outputs_q.get(trigger.entity()) else {
+        return;
+    };
+    let destination = match &output.node {
+        Some(name) => match nodes.find(name) {
+            Some(node) => SocketAddr::new(node.address.into(), ARTNET_PORT),
+            None => return,
+        },
+        None => output.destination,
+    };
+
+    let OutputData {
+        data,
+        bytes_per_pixel,
+        ..
+    } = trigger.event();
+    for chunk in output.patch.split(data.len(), *bytes_per_pixel) {
+        let port_address = output.port_address() + chunk.universe;
+        if port_address > 0x7fff {
+            warn!(
+                "Art-Net output for {} exceeds the last port-address",
+                trigger.entity()
+            );
+            break;
+        }
+
+        let universe = universes.entry((destination, port_address)).or_default();
+        // Rebuilt from this frame's areas, so a shrinking area doesn't leave stale channels
+        if !universe.dirty {
+            universe.data.clear();
+        }
+        let end = chunk.offset + chunk.range.len();
+        if universe.data.len() < end {
+            universe.data.resize(end, 0);
+        }
+        universe.physical = output.physical;
+        universe.sync = output.sync;
+        universe.data[chunk.offset..end].copy_from_slice(&data[chunk.range]);
+        universe.dirty = true;
+    }
+}
+
+fn send_artnet_universes(socket: Res<ArtNetSocket>, mut universes: ResMut<ArtNetUniverses>) {
+    let mut sync_destinations = HashSet::new();
+    for ((destination, port_address), universe) in universes.iter_mut() {
+        if !universe.dirty {
+            continue;
+        }
+        universe.dirty = false;
+        if universe.sync {
+            sync_destinations.insert(*destination);
+        }
+        // A sequence of 0 disables re-ordering on the receiver, so skip it when wrapping
+        universe.sequence = universe.sequence.wrapping_add(1).max(1);
+
+        let command = ArtCommand::Output(Output {
+            sequence: universe.sequence,
+            physical: universe.physical,
+            port_address: (*port_address)
+                .try_into()
+                .expect("port address should be 15 bits"),
+            data: universe.data.clone().into(),
+            ..Output::default()
+        });
+        let bytes = command
+            .write_to_buffer()
+            .expect("Failed to encode ArtDmx packet");
+        if let Err(err) = socket.send_to(&bytes, destination) {
+            warn!("Failed to send Art-Net universe {port_address} to {destination}: {err}");
+        }
+    }
+
+    for destination in sync_destinations {
+        if let Err(err) = socket.send_to(&ART_SYNC_PACKET, destination) {
+            warn!("Failed to send ArtSync to {destination}: {err}");
+        }
+    }
+}
+
+/// Bind the Art-Net port once something needs it, so that apps without Art-Net don't hold it.
+/// A failure isn't retried, as the port is usually held by another app for good.
+fn bind_artnet_socket(
+    mut commands: Commands,
+    outputs_q: Query<(), With<ArtNetOutput>>,
+    poll_settings: Option<Res<ArtPollSettings>>,
+    mut failed: Local<bool>,
+) {
+    if *failed || (outputs_q.is_empty() && poll_settings.is_none()) {
+        return;
+    }
+    match ArtNetSocket::bind((Ipv4Addr::UNSPECIFIED, ARTNET_PORT)) {
+        Ok(socket) => commands.insert_resource(socket),
+        Err(err) => {
+            warn!("Failed to bind the Art-Net port, nothing will be sent: {err}");
+            *failed = true;
+        }
+    }
+}
+
+fn poll_artnet_nodes(
+    time: Res<Time<Real>>,
+    settings: Res<ArtPollSettings>,
+    socket: Res<ArtNetSocket>,
+    mut nodes: ResMut<DiscoveredArtNetNodes>,
+    mut last_poll: Local<Option<Duration>>,
+) {
+    let now = time.elapsed();
+    if last_poll.is_some_and(|last| now.saturating_sub(last) < settings.interval) {
+        return;
+    }
+    *last_poll = Some(now);
+
+    nodes
+        .0
+        .retain(|_, node| now.saturating_sub(node.last_seen) < settings.timeout);
+
+    let bytes = ArtCommand::Poll(Poll::default())
+        .write_to_buffer()
+        .expect("Failed to encode ArtPoll packet");
+    if let Err(err) = socket.send_to(&bytes, settings.destination) {
+        warn!("Failed to send ArtPoll to {}: {err}", settings.destination);
+    }
+}
+
+fn receive_artnet_replies(
+    time: Res<Time<Real>>,
+    socket: Res<ArtNetSocket>,
+    mut nodes: ResMut<DiscoveredArtNetNodes>,
+) {
+    let mut buffer = [0; 1024];
+    loop {
+        let len = match socket.recv_from(&mut buffer) {
+            Ok((len, _)) => len,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+            Err(err) => {
+                warn!("Failed to receive Art-Net packet: {err}");
+                break;
+            }
+        };
+
+        // We also receive our own polls and broadcast data, only replies are of interest
+        let Ok(ArtCommand::PollReply(reply)) = ArtCommand::from_buffer(&buffer[..len]) else {
+            continue;
+        };
+        let node = art_net_node(&reply, time.elapsed());
+        nodes.0.insert((node.address, node.bind_index), node);
+    }
+}
+
+fn art_net_node(reply: &PollReply, last_seen: Duration) -> ArtNetNode {
+    let net = reply.port_address[0] as u16 & 0x7f;
+    let subnet = reply.port_address[1] as u16 & 0xf;
+    let num_ports = (reply.num_ports[1] as usize).min(reply.swout.len());
+    let port_addresses = reply.swout[..num_ports]
+        .iter()
+        .map(|universe| (net << 8) | (subnet << 4) | (*universe as u16 & 0xf))
+        .collect();
+
+    ArtNetNode {
+        address: reply.address,
+        short_name: null_terminated(&reply.short_name),
+        long_name: null_terminated(&reply.long_name),
+        port_addresses,
+        mac: reply.mac,
+        firmware: u16::from_be_bytes(reply.version),
+        bind_index: reply.bind_index,
+        last_seen,
+    }
+}
+
+fn null_terminated(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use std::thread;
+
+    /// An ArtPollReply as a node would send it, built by hand rather than with
+    /// `artnet_protocol` so that parsing is checked against the wire format.
+    fn poll_reply_packet() -> Vec<u8> {
+        let mut packet = b"Art-Net\0".to_vec();
+        packet.extend_from_slice(&[0x00, 0x21]); // OpPollReply, little-endian
+        packet.extend_from_slice(&[127, 0, 0, 1]); // IP address
+        packet.extend_from_slice(&ARTNET_PORT.to_le_bytes());
+        packet.extend_from_slice(&[0x01, 0x02]); // Firmware version
+        packet.extend_from_slice(&[0x01, 0x02]); // Net and subnet switches
+        packet.extend_from_slice(&[0; 2]); // OEM code
+        packet.extend_from_slice(&[0, 0]); // UBEA version and status 1
+        packet.extend_from_slice(&[0; 2]); // ESTA manufacturer
+        let mut short_name = [0; 18];
+        short_name[..8].copy_from_slice(b"Stand-in");
+        packet.extend_from_slice(&short_name);
+        let mut long_name = [0; 64];
+        long_name[..18].copy_from_slice(b"Stand-in test node");
+        packet.extend_from_slice(&long_name);
+        packet.extend_from_slice(&[0; 64]); // Node report
+        packet.extend_from_slice(&[0, 2]); // Number of ports
+        packet.extend_from_slice(&[0x80, 0x80, 0, 0]); // Port types
+        packet.extend_from_slice(&[0; 4]); // Good input
+        packet.extend_from_slice(&[0x80, 0x80, 0, 0]); // Good output
+        packet.extend_from_slice(&[0; 4]); // Input universes
+        packet.extend_from_slice(&[0x03, 0x04, 0, 0]); // Output universes
+        packet.extend_from_slice(&[0; 3]); // Video, macro and remote switches
+        packet.extend_from_slice(&[0; 3]); // Spare
+        packet.push(0); // Style
+        packet.extend_from_slice(&[0x02, 0x00, 0x5e, 0x10, 0x20, 0x30]); // MAC address
+        packet.extend_from_slice(&[127, 0, 0, 1]); // Bind IP address
+        packet.push(1); // Bind index
+        packet.push(0); // Status 2
+        packet.extend_from_slice(&[0; 26]); // Filler
+        packet
+    }
+
+    #[test]
+    fn discovers_nodes_that_answer_polls() {
+        // A node standing in for real hardware on the loopback interface
+        let node = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        node.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+
+        let mut world = World::new();
+        world.insert_resource(Time::<Real>::default());
+        world.insert_resource(ArtNetSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap());
+        world.insert_resource(ArtPollSettings {
+            destination: node.local_addr().unwrap(),
+            ..default()
+        });
+        world.init_resource::<DiscoveredArtNetNodes>();
+
+        world.run_system_once(poll_artnet_nodes);
+
+        let mut buffer = [0; 1024];
+        let (len, controller) = node.recv_from(&mut buffer).unwrap();
+        assert!(matches!(
+            ArtCommand::from_buffer(&buffer[..len]),
+            Ok(ArtCommand::Poll(_))
+        ));
+        node.send_to(&poll_reply_packet(), controller).unwrap();
+
+        // The reply may take a moment to arrive on the non-blocking socket
+        for _ in 0..100 {
+            world.run_system_once(receive_artnet_replies);
+            if world.resource::<DiscoveredArtNetNodes>().iter().count() > 0 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let nodes = world.resource::<DiscoveredArtNetNodes>();
+        assert_eq!(nodes.iter().count(), 1);
+        let node = nodes
+            .find("Stand-in")
+            .expect("node should be found by short name");
+        assert!(nodes.find("Stand-in test node").is_some());
+        assert_eq!(node.address, Ipv4Addr::LOCALHOST);
+        assert_eq!(node.long_name, "Stand-in test node");
+        assert_eq!(node.port_addresses, vec![0x0123, 0x0124]);
+        assert_eq!(node.mac, [0x02, 0x00, 0x5e, 0x10, 0x20, 0x30]);
+        assert_eq!(node.firmware, 0x0102);
+        assert_eq!(node.bind_index, 1);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::pixel::{Pixel, PixelChannel};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// Corrects the colour of a batch of fixtures so that they match each other. The matrix is
+/// applied to linear light after white extraction, followed by a gain and offset per channel.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct ColorCalibration {
+    /// Mixes the red, green and blue channels into the corrected red, green and blue.
+    pub matrix: Mat3,
+    /// The fourth column of the matrix for formats with a white channel, mixing white into the
+    /// corrected red, green and blue to compensate for a tinted white LED.
+    pub white_mix: Vec3,
+    /// The gain and offset of each channel, where channels without one are unchanged.
+    pub trims: HashMap<PixelChannel, ChannelTrim>,
+}
+
+impl Default for ColorCalibration {
+    fn default() -> Self {
+        Self {
+            matrix: Mat3::IDENTITY,
+            white_mix: Vec3::ZERO,
+            trims: HashMap::default(),
+        }
+    }
+}
+
+impl ColorCalibration {
+    pub fn new(matrix: Mat3) -> Self {
+        Self {
+            matrix,
+            ..default()
+        }
+    }
+
+    pub fn white_mix(mut self, white_mix: Vec3) -> Self {
+        self.white_mix = white_mix;
+        self
+    }
+
+    pub fn gain(mut self, channel: PixelChannel, gain: f32) -> Self {
+        self.trims.entry(channel).or_default().gain = gain;
+        self
+    }
+
+    pub fn offset(mut self, channel: PixelChannel, offset: f32) -> Self {
+        self.trims.entry(channel).or_default().offset = offset;
+        self
+    }
+
+    pub(crate) fn apply(&self, pixel: &mut Pixel) {
+        let color = self.matrix * pixel.color() + self.white_mix * pixel.white;
+        pixel.set_color(color.max(Vec3::ZERO));
+    }
+
+    pub(crate) fn trim(&self, channel: PixelChannel, value: f32) -> f32 {
+        match self.trims.get(&channel) {
+            Some(trim) => (value * trim.gain + trim.offset).max(0.0),
+            None => value,
+        }
+    }
+}
+
+/// A linear correction of a single channel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChannelTrim {
+    pub gain: f32,
+    pub offset: f32,
+}
+
+impl Default for ChannelTrim {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            offset: 0.0,
+        }
+    }
+}
+
+/// Calibrates a `LedArea` with the profile registered for its fixture type in
+/// `CalibrationProfiles`. A `ColorCalibration` on the same area takes precedence.
+#[derive(Component, Clone, Debug, PartialEq, Eq, Deref)]
+pub struct FixtureType(pub String);
+
+impl FixtureType {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+}
+
+/// Calibration profiles by fixture type name, shared by every area of that type.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct CalibrationProfiles(HashMap<String, ColorCalibration>);
+
+impl CalibrationProfiles {
+    /// Register the calibration for a fixture type, replacing any existing profile.
+    pub fn insert(&mut self, fixture_type: impl Into<String>, calibration: ColorCalibration) {
+        self.0.insert(fixture_type.into(), calibration);
+    }
+
+    pub fn get(&self, fixture_type: &str) -> Option<&ColorCalibration> {
+        self.0.get(fixture_type)
+    }
+
+    pub fn remove(&mut self, fixture_type: &str) -> Option<ColorCalibration> {
+        self.0.remove(fixture_type)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &ColorCalibration)> {
+        self.0.iter()
+    }
+}
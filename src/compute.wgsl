@@ -6,6 +6,7 @@
 @group(0) @binding(1) var<storage, read_write> average_colors: array<vec4<f32>>;
 @group(0) @binding(2) var<storage, read> leds: array<LedData>;
 @group(0) @binding(3) var<uniform> view: View;
+@group(0) @binding(4) var<storage, read> points: array<vec2<f32>>;
 
 struct LedData {
     start_index: u32,
@@ -14,6 +15,12 @@ struct LedData {
     num_samples: u32,
     total_area_size: vec2<f32>,
     area_position: vec2<f32>,
+    columns: u32,
+    rows: u32,
+    point_offset: u32,
+    use_points: u32,
+    point_radius: f32,
+    point_scale: f32,
 };
 
 @compute @workgroup_size(1)
@@ -25,17 +32,26 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     }
 
     let led_data = leds[bar_index];
-    let segment_width = led_data.total_area_size.x / f32(led_data.num_leds);
+    if (led_data.use_points != 0u) {
+        sample_points(led_data);
+        return;
+    }
+
+    let segment_width = led_data.total_area_size.x / f32(led_data.columns);
     let half_segment_width = segment_width / 2.0;
-    let segment_height = led_data.total_area_size.y;
+    let segment_height = led_data.total_area_size.y / f32(led_data.rows);
 
     let cos_theta = cos(led_data.rotation);
     let sin_theta = -sin(led_data.rotation);
 
+    // LEDs are sampled row by row, a strip being a single row
     for (var led_index: u32 = 0; led_index < led_data.num_leds; led_index++) {
-        let segment_center_x = led_data.area_position.x + (f32(led_index) + 0.5) * segment_width;
-        let start_pos = vec2<f32>(segment_center_x - half_segment_width, led_data.area_position.y);
-        let end_pos = vec2<f32>(segment_center_x + half_segment_width, led_data.area_position.y + segment_height);
+        let column = led_index % led_data.columns;
+        let row = led_index / led_data.columns;
+        let segment_center_x = led_data.area_position.x + (f32(column) + 0.5) * segment_width;
+        let segment_top = led_data.area_position.y + f32(row) * segment_height;
+        let start_pos = vec2<f32>(segment_center_x - half_segment_width, segment_top);
+        let end_pos = vec2<f32>(segment_center_x + half_segment_width, segment_top + segment_height);
 
         var color_sum: vec4<f32> = vec4<f32>(0.0);
         var sample_count: u32 = 0u;
@@ -61,4 +77,40 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
         let avg_color = color_sum / f32(sample_count);
         average_colors[led_data.start_index + led_index] = avg_color;
     }
-}
\ No newline at end of file
+}
+
+// Average the texels within the footprint around each of the area's points
+fn sample_points(led_data: LedData) {
+    let max_texel = vec2<i32>(textureDimensions(inputTexture)) - 1;
+    let radius = led_data.point_radius;
+    let step = 2.0 * radius / f32(max(led_data.num_samples, 1u));
+
+    for (var led_index: u32 = 0; led_index < led_data.num_leds; led_index++) {
+        let center = points[led_data.point_offset + led_index] * led_data.point_scale;
+
+        var color_sum: vec4<f32> = vec4<f32>(0.0);
+        var sample_count: u32 = 0u;
+
+        if (radius > 0.0) {
+            for (var x = -radius + step * 0.5; x < radius; x += step) {
+                for (var y = -radius + step * 0.5; y < radius; y += step) {
+                    if (x * x + y * y > radius * radius) {
+                        continue;
+                    }
+
+                    let texel = clamp(vec2<i32>(center + vec2<f32>(x, y)), vec2<i32>(0), max_texel);
+                    color_sum += textureLoad(inputTexture, texel, 0);
+                    sample_count += 1u;
+                }
+            }
+        }
+
+        // A footprint smaller than a sample still samples its center
+        if (sample_count == 0u) {
+            color_sum = textureLoad(inputTexture, clamp(vec2<i32>(center), vec2<i32>(0), max_texel), 0);
+            sample_count = 1u;
+        }
+
+        average_colors[led_data.start_index + led_index] = color_sum / f32(sample_count);
+    }
+}
@@ -0,0 +1,241 @@
+use crate::output::{encode_output_data, OutputData};
+use crate::socket::{bind_output_socket, bind_udp_socket, OutputSocket};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+
+/// The UDP port used by DDP.
+pub const DDP_PORT: u16 = 4048;
+
+/// The largest payload that fits in a single DDP packet.
+const DDP_MAX_DATA: usize = 1440;
+
+const DDP_VERSION_1: u8 = 0x40;
+const DDP_PUSH: u8 = 0x01;
+const DDP_TYPE_RGB24: u8 = 0x0b;
+const DDP_TYPE_RGBW32: u8 = 0x1b;
+const DDP_ID_DISPLAY: u8 = 1;
+
+pub struct DdpPlugin;
+
+impl Plugin for DdpPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DdpDevices>()
+            .observe(patch_ddp_output)
+            .add_systems(
+                PreUpdate,
+                bind_output_socket::<DdpSocket, DdpOutput>
+                    .run_if(not(resource_exists::<DdpSocket>)),
+            )
+            .add_systems(
+                First,
+                send_ddp_devices
+                    .after(encode_output_data)
+                    .run_if(resource_exists::<DdpSocket>),
+            );
+    }
+}
+
+/// The socket used to send DDP packets. Insert this resource to bind to a specific
+/// interface, otherwise an ephemeral port is bound on all interfaces once there is a
+/// `DdpOutput`.
+#[derive(Resource, Deref)]
+pub struct DdpSocket(UdpSocket);
+
+impl DdpSocket {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        bind_udp_socket(addr).map(Self)
+    }
+}
+
+impl OutputSocket for DdpSocket {
+    const PROTOCOL: &'static str = "DDP";
+
+    fn bind_default() -> io::Result<Self> {
+        Self::bind((Ipv4Addr::UNSPECIFIED, 0))
+    }
+}
+
+/// Sends a `LedArea`'s pixel data to a DDP device. Several areas may share a device by
+/// writing to different offsets.
+/// Areas with 16-bit `OutputDepth` send only the coarse byte of each channel.
+#[derive(Component, Clone, Debug)]
+pub struct DdpOutput {
+    pub destination: SocketAddr,
+    /// The channel offset of the area's first pixel on the device.
+    pub offset: usize,
+}
+
+impl DdpOutput {
+    pub fn new(ip: impl Into<IpAddr>) -> Self {
+        Self {
+            destination: SocketAddr::new(ip.into(), DDP_PORT),
+            offset: 0,
+        }
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+}
+
+#[derive(Default)]
+struct DdpDevice {
+    sequence: u8,
+    channels_per_pixel: usize,
+    data: Vec<u8>,
+    dirty: bool,
+}
+
+#[derive(Resource, Deref, DerefMut, Default)]
+struct DdpDevices(HashMap<SocketAddr, DdpDevice>);
+
+fn patch_ddp_output(
+    trigger: Trigger<OutputData>,
+    outputs_q: Query<&DdpOutput>,
+    mut devices: ResMut<DdpDevices>,
+) {
+    let Ok(output) = outputs_q.get(trigger.entity()) else {
+        return;
+    };
+
+    let output_data = trigger.event();
+    let device = devices.entry(output.destination).or_default();
+    // The first area patched each frame starts the device's data afresh, and sets the data type
+    // that the packets carry
+    if !device.dirty {
+        device.channels_per_pixel = output_data.channels();
+        device.data.clear();
+    } else if device.channels_per_pixel != output_data.channels() {
+        warn_once!(
+            "DDP output for {} has a different pixel format to the other areas of {}, skipping",
+            trigger.entity(),
+            output.destination
+        );
+        return;
+    }
+
+    let data = output_data.eight_bit();
+    let end = output.offset + data.len();
+    if device.data.len() < end {
+        device.data.resize(end, 0);
+    }
+    device.data[output.offset..end].copy_from_slice(&data);
+    device.dirty = true;
+}
+
+fn send_ddp_devices(socket: Res<DdpSocket>, mut devices: ResMut<DdpDevices>) {
+    for (destination, device) in devices.iter_mut() {
+        if !device.dirty {
+            continue;
+        }
+        device.dirty = false;
+        device.sequence = next_sequence(device.sequence);
+
+        for packet in ddp_packets(device.sequence, device.channels_per_pixel, &device.data) {
+            if let Err(err) = socket.send_to(&packet, destination) {
+                warn!("Failed to send DDP packet to {destination}: {err}");
+            }
+        }
+    }
+}
+
+/// Sequence numbers cycle through 1-15, 0 means the receiver shouldn't check them.
+fn next_sequence(sequence: u8) -> u8 {
+    sequence % 15 + 1
+}
+
+/// Split a device's data into packets of whole pixels, where only the last tells the device to
+/// display the frame.
+fn ddp_packets(sequence: u8, channels_per_pixel: usize, data: &[u8]) -> Vec<Vec<u8>> {
+    let data_type = match channels_per_pixel {
+        3 => DDP_TYPE_RGB24,
+        4 => DDP_TYPE_RGBW32,
+        _ => 0,
+    };
+    let chunk_size = match channels_per_pixel {
+        0 => DDP_MAX_DATA,
+        n => (DDP_MAX_DATA / n).max(1) * n,
+    };
+
+    let num_chunks = data.len().div_ceil(chunk_size);
+    data.chunks(chunk_size)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let offset = (i * chunk_size) as u32;
+            let flags = if i + 1 == num_chunks {
+                DDP_VERSION_1 | DDP_PUSH
+            } else {
+                DDP_VERSION_1
+            };
+
+            let mut packet = Vec::with_capacity(10 + chunk.len());
+            packet.extend_from_slice(&[flags, sequence, data_type, DDP_ID_DISPLAY]);
+            packet.extend_from_slice(&offset.to_be_bytes());
+            packet.extend_from_slice(&(chunk.len() as u16).to_be_bytes());
+            packet.extend_from_slice(chunk);
+            packet
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequence_cycles_through_one_to_fifteen() {
+        let mut sequence = 0;
+        let sequences = (0..16)
+            .map(|_| {
+                sequence = next_sequence(sequence);
+                sequence
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(sequences[..15], (1..=15).collect::<Vec<_>>()[..]);
+        assert_eq!(sequences[15], 1);
+    }
+
+    #[test]
+    fn single_packet_frames_push() {
+        let packets = ddp_packets(3, 3, &[1, 2, 3, 4, 5, 6]);
+        assert_eq!(
+            packets,
+            vec![vec![0x41, 3, 0x0b, 1, 0, 0, 0, 0, 0, 6, 1, 2, 3, 4, 5, 6]]
+        );
+    }
+
+    #[test]
+    fn long_frames_split_on_whole_pixels() {
+        // 1440 bytes is 360 RGBW pixels, but only 480 RGB pixels
+        let data = (0..2000).map(|i| i as u8).collect::<Vec<_>>();
+        let packets = ddp_packets(7, 4, &data);
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0][..10], [0x40, 7, 0x1b, 1, 0, 0, 0, 0, 0x05, 0xa0]);
+        assert_eq!(packets[0][10..], data[..1440]);
+        // The last packet starts at its byte offset and pushes the frame
+        assert_eq!(
+            packets[1][..10],
+            [0x41, 7, 0x1b, 1, 0, 0, 0x05, 0xa0, 0x02, 0x30]
+        );
+        assert_eq!(packets[1][10..], data[1440..]);
+
+        let packets = ddp_packets(7, 3, &data);
+        assert_eq!(packets[0].len(), 10 + 1440);
+        assert_eq!(packets[1][4..8], 1440u32.to_be_bytes());
+    }
+
+    #[test]
+    fn odd_pixel_sizes_stay_whole() {
+        let data = vec![0; 1500];
+        let packets = ddp_packets(1, 5, &data);
+        assert_eq!(packets[0].len(), 10 + 1440);
+        let packets = ddp_packets(1, 6, &data);
+        assert_eq!(packets[0].len(), 10 + 1440);
+        let packets = ddp_packets(1, 7, &data);
+        assert_eq!(packets[0].len(), 10 + 1435);
+        assert_eq!(packets[0][2], 0);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::output::{encode_output_data, OutputData, DMX_START_CODE, DMX_UNIVERSE_SIZE};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use crossbeam_channel::{Receiver, Sender};
+use serialport::SerialPort;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const ENTTEC_START_OF_MESSAGE: u8 = 0x7e;
+const ENTTEC_END_OF_MESSAGE: u8 = 0xe7;
+/// The "Output Only Send DMX Packet" request label.
+const ENTTEC_SEND_DMX: u8 = 6;
+/// The widget rejects packets with fewer channels than this.
+const ENTTEC_MIN_CHANNELS: usize = 24;
+const ENTTEC_BAUD_RATE: u32 = 57600;
+const ENTTEC_REOPEN_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct EnttecPlugin;
+
+impl Plugin for EnttecPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EnttecPorts>()
+            .observe(patch_enttec_output)
+            .add_systems(First, send_enttec_ports.after(encode_output_data));
+    }
+}
+
+/// Sends a `LedArea`'s pixel data to an Enttec DMX USB Pro style widget on a serial port.
+/// Several areas may share a port by starting at different channels.
+#[derive(Component, Clone, Debug)]
+pub struct EnttecOutput {
+    /// The serial device, e.g. `/dev/ttyUSB0` or `COM3`.
+    pub path: PathBuf,
+    /// The 1-based channel of the area's first pixel.
+    pub start_channel: u16,
+}
+
+impl EnttecOutput {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            start_channel: 1,
+        }
+    }
+
+    pub fn start_channel(mut self, start_channel: u16) -> Self {
+        self.start_channel = start_channel;
+        self
+    }
+}
+
+struct EnttecPort {
+    universe: Vec<u8>,
+    dirty: bool,
+    sender: Sender<Vec<u8>>,
+}
+
+/// The serial ports patched by outputs. Each is written by a background thread so that the main
+/// thread never blocks on the device.
+#[derive(Resource, Deref, DerefMut, Default)]
+struct EnttecPorts(HashMap<PathBuf, EnttecPort>);
+
+fn patch_enttec_output(
+    trigger: Trigger<OutputData>,
+    outputs_q: Query<&EnttecOutput>,
+    mut ports: ResMut<EnttecPorts>,
+) {
+    let Ok(output) = outputs_q.get(trigger.entity()) else {
+        return;
+    };
+
+    let port = ports
+        .entry(output.path.clone())
+        .or_insert_with(|| EnttecPort {
+            universe: Vec::new(),
+            dirty: false,
+            sender: spawn_enttec_writer(output.path.clone()),
+        });
+
+    let data = &trigger.event().data;
+    let offset = output.start_channel.clamp(1, DMX_UNIVERSE_SIZE as u16) as usize - 1;
+    let end = (offset + data.len()).min(DMX_UNIVERSE_SIZE);
+    if offset + data.len() > DMX_UNIVERSE_SIZE {
+        warn_once!(
+            "Enttec output for {} exceeds the universe, truncating",
+            trigger.entity()
+        );
+    }
+
+    // Rebuilt from this frame's areas, so a shrinking area doesn't leave stale channels
+    if !port.dirty {
+        port.universe.clear();
+    }
+    if port.universe.len() < end {
+        port.universe.resize(end, 0);
+    }
+    port.universe[offset..end].copy_from_slice(&data[..end - offset]);
+    port.dirty = true;
+}
+
+fn send_enttec_ports(mut ports: ResMut<EnttecPorts>) {
+    for port in ports.values_mut() {
+        if !port.dirty {
+            continue;
+        }
+        port.dirty = false;
+        let _ = port.sender.send(send_dmx_packet(&port.universe));
+    }
+}
+
+/// Frame a universe as a Send DMX request: the start of message, the label, the little-endian
+/// length of the start code and channels, the data and the end of message.
+fn send_dmx_packet(universe: &[u8]) -> Vec<u8> {
+    let channels = universe.len().max(ENTTEC_MIN_CHANNELS);
+    let len = (channels + 1) as u16;
+    let mut packet = Vec::with_capacity(channels + 6);
+    packet.push(ENTTEC_START_OF_MESSAGE);
+    packet.push(ENTTEC_SEND_DMX);
+    packet.extend_from_slice(&len.to_le_bytes());
+    packet.push(DMX_START_CODE);
+    packet.extend_from_slice(universe);
+    packet.resize(channels + 5, 0);
+    packet.push(ENTTEC_END_OF_MESSAGE);
+    packet
+}
+
+fn spawn_enttec_writer(path: PathBuf) -> Sender<Vec<u8>> {
+    let (s, r) = crossbeam_channel::unbounded();
+    std::thread::Builder::new()
+        .name(format!("enttec-{}", path.display()))
+        .spawn(move || run_enttec_writer(path, r))
+        .expect("Failed to spawn Enttec writer thread");
+    s
+}
+
+fn run_enttec_writer(path: PathBuf, receiver: Receiver<Vec<u8>>) {
+    let mut port: Option<Box<dyn SerialPort>> = None;
+    let mut last_attempt: Option<Instant> = None;
+
+    while let Ok(packet) = receiver.recv() {
+        // Only the most recent frame is worth writing
+        let packet = receiver.try_iter().last().unwrap_or(packet);
+
+        if port.is_none() {
+            if last_attempt.is_some_and(|last| last.elapsed() < ENTTEC_REOPEN_INTERVAL) {
+                continue;
+            }
+            last_attempt = Some(Instant::now());
+
+            match serialport::new(path.to_string_lossy(), ENTTEC_BAUD_RATE)
+                .timeout(Duration::from_secs(1))
+                .open()
+            {
+                Ok(p) => {
+                    info!("Opened Enttec widget at {}", path.display());
+                    port = Some(p);
+                }
+                Err(err) => {
+                    warn!("Failed to open Enttec widget at {}: {err}", path.display());
+                    continue;
+                }
+            }
+        }
+
+        let Some(p) = port.as_mut() else {
+            continue;
+        };
+        if let Err(err) = p.write_all(&packet) {
+            warn!("Lost Enttec widget at {}: {err}", path.display());
+            port = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::thread;
+
+    #[test]
+    fn send_dmx_pads_short_universes() {
+        let packet = send_dmx_packet(&[10, 20, 30]);
+        assert_eq!(packet.len(), ENTTEC_MIN_CHANNELS + 6);
+        assert_eq!(packet[..8], [0x7e, 6, 25, 0, 0, 10, 20, 30]);
+        assert!(packet[8..packet.len() - 1].iter().all(|v| *v == 0));
+        assert_eq!(packet.last(), Some(&0xe7));
+    }
+
+    #[test]
+    fn send_dmx_frames_a_full_universe() {
+        let universe = (0..DMX_UNIVERSE_SIZE).map(|i| i as u8).collect::<Vec<_>>();
+        let packet = send_dmx_packet(&universe);
+        assert_eq!(packet.len(), DMX_UNIVERSE_SIZE + 6);
+        // 513 bytes of data, including the start code
+        assert_eq!(packet[..5], [0x7e, 6, 0x01, 0x02, 0]);
+        assert_eq!(packet[5..5 + DMX_UNIVERSE_SIZE], universe[..]);
+        assert_eq!(packet.last(), Some(&0xe7));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn writer_sends_packets_to_the_port() {
+        // A pseudo terminal stands in for the widget, with the writer on the slave end
+        let (mut master, slave) = serialport::TTYPort::pair().unwrap();
+        let path = PathBuf::from(slave.name().expect("slave should have a path"));
+        drop(slave);
+
+        let sender = spawn_enttec_writer(path);
+        let packet = send_dmx_packet(&[10, 20, 30]);
+        sender.send(packet.clone()).unwrap();
+
+        master.set_timeout(Duration::from_millis(100)).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut received = Vec::new();
+        let mut buffer = [0; 64];
+        while received.len() < packet.len() {
+            assert!(Instant::now() < deadline, "writer didn't send the packet");
+            match master.read(&mut buffer) {
+                Ok(len) => received.extend_from_slice(&buffer[..len]),
+                // Reads fail until the writer has opened the slave end
+                Err(_) => thread::sleep(Duration::from_millis(10)),
+            }
+        }
+        assert_eq!(received, packet);
+    }
+}
@@ -2,6 +2,7 @@ use ::nannou::prelude::bevy_render::render_phase::ViewBinnedRenderPhases;
 use ::nannou::prelude::render::NannouCamera;
 use std::borrow::Cow;
 
+pub use artnet_protocol;
 use bevy::asset::load_internal_asset;
 use bevy::core_pipeline::bloom::BloomSettings;
 use bevy::core_pipeline::core_3d::graph::{Core3d, Node3d};
@@ -57,13 +58,60 @@ use bevy_mod_picking::DefaultPickingPlugins;
 use crossbeam_channel::{Receiver, Sender};
 pub use sacn;
 
+use crate::artnet::ArtNetPlugin;
+use crate::ddp::DdpPlugin;
+use crate::enttec::EnttecPlugin;
+use crate::opc::OpcPlugin;
+use crate::output::OutputPlugin;
+use crate::path::PathPlugin;
+use crate::point_cloud::PointCloudPlugin;
+use crate::ring::RingPlugin;
+use crate::sacn_src::SacnPlugin;
 use crate::ui::UiPlugin;
+use crate::wled::WledPlugin;
+use crate::xmodel::XModelPlugin;
 
 mod app;
+mod artnet;
+mod calibration;
+mod ddp;
+mod enttec;
+mod lut;
+mod matrix;
+mod opc;
+mod output;
+mod path;
+mod pixel;
+mod point_cloud;
+mod points;
+mod power;
+mod ring;
 mod sacn_src;
+mod socket;
+mod text_asset;
 mod ui;
+mod wled;
+mod xmodel;
 
 pub use crate::app::*;
+pub use crate::artnet::*;
+pub use crate::calibration::*;
+pub use crate::ddp::*;
+pub use crate::enttec::*;
+pub use crate::lut::*;
+pub use crate::matrix::*;
+pub use crate::opc::*;
+pub use crate::output::*;
+pub use crate::path::*;
+pub use crate::pixel::*;
+pub use crate::point_cloud::*;
+pub use crate::points::*;
+pub use crate::power::*;
+pub use crate::ring::*;
+pub use crate::sacn_src::*;
+pub use crate::text_asset::*;
+pub use crate::wled::*;
+pub use crate::xmodel::*;
 
 const COMPUTE_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(966169125558327);
 const MATERIAL_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(116169934631328);
@@ -87,11 +135,27 @@ impl Plugin for NannouPixelmapPlugin {
 
         app.add_plugins((
             UiPlugin,
+            OutputPlugin,
+            PowerPlugin,
+            ArtNetPlugin,
+            SacnPlugin,
+            DdpPlugin,
+            OpcPlugin,
+            WledPlugin,
+            EnttecPlugin,
+            PathPlugin,
+            RingPlugin,
+            PointCloudPlugin,
+            XModelPlugin,
             DefaultPickingPlugins,
-            ExtractComponentPlugin::<LedArea>::default(),
-            ExtractComponentPlugin::<ScreenTexture>::default(),
-            ExtractComponentPlugin::<ScreenTextureCamera>::default(),
-            ExtractComponentPlugin::<ScreenMaterialCamera>::default(),
+            (
+                ExtractComponentPlugin::<LedArea>::default(),
+                ExtractComponentPlugin::<LedMatrix>::default(),
+                ExtractComponentPlugin::<LedPoints>::default(),
+                ExtractComponentPlugin::<ScreenTexture>::default(),
+                ExtractComponentPlugin::<ScreenTextureCamera>::default(),
+                ExtractComponentPlugin::<ScreenMaterialCamera>::default(),
+            ),
         ))
         .add_systems(PostUpdate, check_visibility::<With<LedArea>>)
         .add_systems(
@@ -153,6 +217,7 @@ impl Plugin for NannouPixelmapPlugin {
             .init_resource::<WorkItemBuffers>()
             .init_resource::<GpuOutputBuffers>()
             .init_resource::<CpuReadbackBuffers>()
+            .init_resource::<PointBuffers>()
             .init_resource::<ComputeBindGroups>()
             .add_systems(
                 Render,
@@ -201,6 +266,9 @@ struct GpuOutputBuffers(EntityHashMap<UninitBufferVec<LinearRgba>>);
 #[derive(Resource, Deref, DerefMut, Default)]
 struct CpuReadbackBuffers(EntityHashMap<RawBufferVec<LinearRgba>>);
 
+#[derive(Resource, Deref, DerefMut, Default)]
+struct PointBuffers(EntityHashMap<BufferVec<Vec2>>);
+
 #[derive(Component, ExtractComponent, Clone)]
 struct ScreenTextureCamera;
 
@@ -273,25 +341,71 @@ pub struct LedMaterial {
     pub position: Vec2,
     #[uniform(0)]
     pub size: Vec2,
+    #[uniform(0)]
+    pub columns: u32,
+    #[uniform(0)]
+    pub rows: u32,
+    #[uniform(0)]
+    pub use_points: u32,
+    #[uniform(0)]
+    pub point_offset: u32,
+    #[uniform(0)]
+    pub point_radius: f32,
     #[storage(1, read_only, buffer)]
     pub color_buffer: Buffer,
+    #[storage(2, read_only, buffer)]
+    pub point_buffer: Buffer,
+}
+
+impl LedMaterial {
+    /// The number of instances drawn, one per LED for point areas.
+    fn instances(&self) -> u32 {
+        if self.use_points != 0 {
+            self.count
+        } else {
+            1
+        }
+    }
 }
 
 #[derive(Component)]
 pub struct LedMaterialBindGroup(pub BindGroup);
 
+#[derive(Component)]
+pub struct LedMaterialInstances(pub u32);
+
 #[derive(Component, Default, Debug)]
 pub struct ViewLeds {
     work_items: EntityHashMap<LedWorkItem>,
     materials: EntityHashMap<LedMaterial>,
+    points: Vec<Vec2>,
 }
 
 // -------------------------
 // Systems
 // -------------------------
 
-fn send_led_data(mut commands: Commands, mut receiver: ResMut<LedDataReceiver>) {
+fn send_led_data(
+    mut commands: Commands,
+    mut receiver: ResMut<LedDataReceiver>,
+    matrix_q: Query<&LedMatrix>,
+) {
     while let Ok((entity, data)) = receiver.0.try_recv() {
+        // Matrices are sampled row by row, reorder them into the order they're wired
+        let data = match matrix_q.get(entity) {
+            Ok(matrix) => match matrix.rewire(&data) {
+                Some(data) => data,
+                None => {
+                    warn!(
+                        "Matrix {entity} has {} LEDs but {} were read back",
+                        matrix.count(),
+                        data.len() / 4
+                    );
+                    continue;
+                }
+            },
+            Err(_) => data,
+        };
         commands.trigger_targets(ReceivedData(data), entity);
     }
 }
@@ -503,20 +617,35 @@ fn queue_leds(
     mut commands: Commands,
     views: Query<(Entity, &ExtractedView, &VisibleEntities), With<ScreenMaterialCamera>>,
     gpu_output: Res<GpuOutputBuffers>,
-    leds: Query<&LedArea>,
+    point_buffers: Res<PointBuffers>,
+    leds: Query<(&LedArea, Option<&LedMatrix>, Option<&LedPoints>)>,
 ) {
     for (view_entity, view, visible_entities) in views.iter() {
         let is_orthographic = view.clip_from_view.w_axis.w == 1.0;
         let mut view_leds = ViewLeds::default();
+        let mut idx = 0;
         for visible in visible_entities.iter::<With<LedArea>>() {
-            let mut idx = 0;
-            if let Ok(led) = leds.get(*visible) {
+            if let Ok((led, matrix, points)) = leds.get(*visible) {
+                let (columns, rows) = match (points, matrix) {
+                    (Some(points), _) => (points.points.len() as u32, 1),
+                    (None, Some(matrix)) => (matrix.columns, matrix.rows),
+                    (None, None) => (led.count, 1),
+                };
+                let num_leds = columns * rows;
+                let point_offset = view_leds.points.len() as u32;
+                if let Some(points) = points {
+                    view_leds.points.extend_from_slice(&points.points);
+                }
+                let point_radius = points.map_or(0.0, |points| points.radius);
+                // Every area in the view writes to its own range of the output buffer
+                let start_index = idx;
+                idx += num_leds;
                 view_leds.work_items.insert(
                     *visible,
                     LedWorkItem {
-                        start_index: 0,
+                        start_index,
                         rotation: led.rotation,
-                        num_leds: led.count,
+                        num_leds,
                         num_samples: led.num_samples,
                         total_area_size: if is_orthographic {
                             led.size / 2.0
@@ -528,6 +657,16 @@ fn queue_leds(
                         } else {
                             led.position
                         },
+                        columns,
+                        rows,
+                        point_offset,
+                        use_points: points.is_some() as u32,
+                        point_radius: if is_orthographic {
+                            point_radius / 2.0
+                        } else {
+                            point_radius
+                        },
+                        point_scale: if is_orthographic { 0.5 } else { 1.0 },
                     },
                 );
 
@@ -539,20 +678,31 @@ fn queue_leds(
                     warn!("No buffer for view {view_entity}");
                     continue;
                 };
+                let Some(point_buffer) = point_buffers
+                    .get(&view_entity)
+                    .and_then(|point_buffer| point_buffer.buffer())
+                else {
+                    warn!("No point buffer for view {view_entity}");
+                    continue;
+                };
 
                 view_leds.materials.insert(
                     *visible,
                     LedMaterial {
-                        offset: idx as u32,
+                        offset: start_index,
                         rotation: led.rotation,
-                        count: led.count,
+                        count: num_leds,
                         position: led.position,
                         size: led.size,
+                        columns,
+                        rows,
+                        use_points: points.is_some() as u32,
+                        point_offset,
+                        point_radius,
                         color_buffer: buffer.clone(),
+                        point_buffer: point_buffer.clone(),
                     },
                 );
-
-                idx += 1;
             }
         }
 
@@ -564,9 +714,22 @@ fn prepare_buffers(
     mut work_items: ResMut<WorkItemBuffers>,
     mut gpu_output: ResMut<GpuOutputBuffers>,
     mut cpu_readback: ResMut<CpuReadbackBuffers>,
+    mut point_buffers: ResMut<PointBuffers>,
     mut views: Query<(Entity, &mut ViewLeds), With<ExtractedView>>,
 ) {
     for (entity, mut leds) in &mut views {
+        let point_buffer = point_buffers
+            .entry(entity)
+            .or_insert_with(|| BufferVec::new(BufferUsages::COPY_DST | BufferUsages::STORAGE));
+        point_buffer.clear();
+        for point in leds.points.drain(..) {
+            point_buffer.push(point);
+        }
+        // The buffer is always bound, so it can't be empty
+        if point_buffer.is_empty() {
+            point_buffer.push(Vec2::ZERO);
+        }
+
         let (mut work_items, mut gpu_output, cpu_readback) = (
             work_items
                 .entry(entity)
@@ -607,6 +770,7 @@ fn prepare_bind_groups(
     mut work_items: ResMut<WorkItemBuffers>,
     mut gpu_output: ResMut<GpuOutputBuffers>,
     mut cpu_readback: ResMut<CpuReadbackBuffers>,
+    mut point_buffers: ResMut<PointBuffers>,
     mut compute_bind_groups: ResMut<ComputeBindGroups>,
 ) {
     for (entity, screen_texture, view_leds) in &views {
@@ -626,12 +790,16 @@ fn prepare_bind_groups(
         let Some(mut cpu_readback) = cpu_readback.get_mut(&entity) else {
             continue;
         };
+        let Some(point_buffer) = point_buffers.get_mut(&entity) else {
+            continue;
+        };
         if gpu_output.is_empty() || work_items.is_empty() {
             continue;
         }
 
         gpu_output.write_buffer(&render_device);
         work_items.write_buffer(&render_device, &render_queue);
+        point_buffer.write_buffer(&render_device, &render_queue);
         cpu_readback.reserve(gpu_output.len(), &render_device);
 
         let bind_group = render_device.create_bind_group(
@@ -648,6 +816,10 @@ fn prepare_bind_groups(
                     .expect("buffer should exist")
                     .as_entire_binding(),
                 view_uniforms_binding.into_binding(),
+                point_buffer
+                    .buffer()
+                    .expect("buffer should exist")
+                    .as_entire_binding(),
             )),
         );
 
@@ -662,23 +834,13 @@ fn prepare_bind_groups(
                     &fallback_img,
                 )
                 .expect("Failed to create bind group");
-            commands
-                .entity(*entity)
-                .insert(LedMaterialBindGroup(bind_group.bind_group.clone()));
+            commands.entity(*entity).insert((
+                LedMaterialBindGroup(bind_group.bind_group.clone()),
+                LedMaterialInstances(material.instances()),
+            ));
         }
     }
 }
-fn f32_to_u8(value: f32) -> u8 {
-    // Clamp the value to the range [0.0, 1.0] to ensure valid u8 conversion
-    let clamped_value = value.clamp(0.0, 1.0);
-    // Scale the clamped value to the range [0, 255] and cast to u8
-    (clamped_value * 255.0).round() as u8
-}
-
-fn f32_vec_to_u8_vec(values: Vec<f32>) -> Vec<u8> {
-    values.iter().map(|&v| f32_to_u8(v)).collect()
-}
-
 fn map_and_read_buffer(
     render_device: Res<RenderDevice>,
     cpu_readback_buffers: Res<CpuReadbackBuffers>,
@@ -712,10 +874,15 @@ fn map_and_read_buffer(
                 .map(|chunk| f32::from_ne_bytes(chunk.try_into().expect("should be a u32")))
                 .collect::<Vec<f32>>();
 
+            // Each LED is a `LinearRgba`, so its range of floats is four times its range of LEDs
             for (led_entity, led) in view_leds.materials.iter() {
-                let led_data =
-                    data[led.offset as usize..(led.offset + led.count) as usize].to_vec();
-                let _ = sender.send((*led_entity, led_data));
+                let start = led.offset as usize * 4;
+                let end = (led.offset + led.count) as usize * 4;
+                let Some(led_data) = data.get(start..end) else {
+                    warn!("Readback is too short for the LEDs of {led_entity}");
+                    continue;
+                };
+                let _ = sender.send((*led_entity, led_data.to_vec()));
             }
         }
         buffer.unmap();
@@ -779,6 +946,12 @@ pub struct LedWorkItem {
     num_samples: u32,
     total_area_size: Vec2,
     area_position: Vec2,
+    columns: u32,
+    rows: u32,
+    point_offset: u32,
+    use_points: u32,
+    point_radius: f32,
+    point_scale: f32,
 }
 
 impl FromWorld for ComputePipeline {
@@ -793,6 +966,7 @@ impl FromWorld for ComputePipeline {
                     storage_buffer::<LinearRgba>(false),
                     storage_buffer_read_only::<LedWorkItem>(false),
                     uniform_buffer::<ViewUniform>(true),
+                    storage_buffer_read_only::<Vec2>(false),
                 ),
             ),
         );
@@ -909,16 +1083,17 @@ pub struct DrawMaterial;
 impl<P: PhaseItem> RenderCommand<P> for DrawMaterial {
     type Param = ();
     type ViewQuery = ();
-    type ItemQuery = ();
+    type ItemQuery = Read<LedMaterialInstances>;
 
     fn render<'w>(
         _item: &P,
         _view: ROQueryItem<'w, Self::ViewQuery>,
-        _entity: Option<ROQueryItem<'w, Self::ItemQuery>>,
+        instances: Option<ROQueryItem<'w, Self::ItemQuery>>,
         _param: SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
-        pass.draw(0..4, 0..1);
+        let instances = instances.map_or(1, |instances| instances.0);
+        pass.draw(0..4, 0..instances);
         RenderCommandResult::Success
     }
 }
@@ -935,6 +1110,7 @@ impl FromWorld for LedMaterialPipeline {
         let render_device = world.resource::<RenderDevice>();
         let mut layout_entries = LedMaterial::bind_group_layout_entries(render_device);
         layout_entries[0].visibility = ShaderStages::VERTEX | ShaderStages::FRAGMENT;
+        layout_entries[2].visibility = ShaderStages::VERTEX;
         let layout = render_device.create_bind_group_layout(LedMaterial::label(), &layout_entries);
         LedMaterialPipeline {
             mesh_pipeline: mesh_pipeline.clone(),
@@ -0,0 +1,279 @@
+use crate::pixel::Pixel;
+use crate::text_asset::{TextAsset, TextAssetLoader};
+use bevy::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+
+/// A 3D colour lookup table loaded from a `.cube` file, used to grade the LED output
+/// separately from the screen.
+#[derive(Asset, TypePath, Clone, Debug, PartialEq)]
+pub struct CubeLut {
+    pub title: Option<String>,
+    /// The number of entries along each axis.
+    pub size: usize,
+    pub domain_min: Vec3,
+    pub domain_max: Vec3,
+    /// The output colour of every entry, with red changing fastest and blue slowest.
+    pub data: Vec<Vec3>,
+}
+
+impl CubeLut {
+    /// Look up a colour, interpolating trilinearly between the surrounding entries.
+    pub fn sample(&self, color: Vec3) -> Vec3 {
+        if self.size < 2 {
+            return self.data.first().copied().unwrap_or(color);
+        }
+
+        let max = (self.size - 1) as f32;
+        let range = (self.domain_max - self.domain_min).max(Vec3::splat(f32::EPSILON));
+        let position = ((color - self.domain_min) / range).clamp(Vec3::ZERO, Vec3::ONE) * max;
+        let index = position.floor().min(Vec3::splat(max - 1.0));
+        let t = position - index;
+        let [r, g, b] = index.to_array().map(|i| i as usize);
+
+        let entry = |r: usize, g: usize, b: usize| self.data[r + (g + b * self.size) * self.size];
+        let c00 = entry(r, g, b).lerp(entry(r + 1, g, b), t.x);
+        let c10 = entry(r, g + 1, b).lerp(entry(r + 1, g + 1, b), t.x);
+        let c01 = entry(r, g, b + 1).lerp(entry(r + 1, g, b + 1), t.x);
+        let c11 = entry(r, g + 1, b + 1).lerp(entry(r + 1, g + 1, b + 1), t.x);
+        c00.lerp(c10, t.y).lerp(c01.lerp(c11, t.y), t.z)
+    }
+
+    pub(crate) fn apply(&self, pixel: &mut Pixel) {
+        pixel.set_color(self.sample(pixel.color()).max(Vec3::ZERO));
+    }
+}
+
+impl FromStr for CubeLut {
+    type Err = CubeLutError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut title = None;
+        let mut size = None;
+        let mut domain_min = Vec3::ZERO;
+        let mut domain_max = Vec3::ONE;
+        let mut data = Vec::new();
+
+        for (i, line) in s.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            match keyword {
+                "TITLE" => title = Some(rest.trim_matches('"').to_string()),
+                "LUT_3D_SIZE" => {
+                    let n = rest
+                        .parse::<usize>()
+                        .map_err(|_| CubeLutError::InvalidLine(line_number))?;
+                    size = Some(n);
+                }
+                "LUT_1D_SIZE" => return Err(CubeLutError::Unsupported("1D LUTs")),
+                "DOMAIN_MIN" => domain_min = parse_vec3(rest, line_number)?,
+                "DOMAIN_MAX" => domain_max = parse_vec3(rest, line_number)?,
+                // The older form of the domain, the same for every channel
+                "LUT_3D_INPUT_RANGE" => {
+                    let mut values = rest.split_whitespace().map(str::parse::<f32>);
+                    match (values.next(), values.next(), values.next()) {
+                        (Some(Ok(min)), Some(Ok(max)), None) => {
+                            domain_min = Vec3::splat(min);
+                            domain_max = Vec3::splat(max);
+                        }
+                        _ => return Err(CubeLutError::InvalidLine(line_number)),
+                    }
+                }
+                // Some writers add keywords of their own, which are safe to skip
+                _ if keyword.chars().next().is_some_and(char::is_alphabetic) => {}
+                _ => data.push(parse_vec3(line, line_number)?),
+            }
+        }
+
+        let size = size.ok_or(CubeLutError::MissingSize)?;
+        let expected = size * size * size;
+        if data.len() != expected {
+            return Err(CubeLutError::WrongEntryCount {
+                expected,
+                found: data.len(),
+            });
+        }
+
+        Ok(Self {
+            title,
+            size,
+            domain_min,
+            domain_max,
+            data,
+        })
+    }
+}
+
+fn parse_vec3(s: &str, line_number: usize) -> Result<Vec3, CubeLutError> {
+    let mut values = s.split_whitespace().map(str::parse::<f32>);
+    match (values.next(), values.next(), values.next(), values.next()) {
+        (Some(Ok(r)), Some(Ok(g)), Some(Ok(b)), None) => Ok(Vec3::new(r, g, b)),
+        _ => Err(CubeLutError::InvalidLine(line_number)),
+    }
+}
+
+#[derive(Debug)]
+pub enum CubeLutError {
+    /// A line that couldn't be parsed, by its 1-based line number.
+    InvalidLine(usize),
+    MissingSize,
+    WrongEntryCount {
+        expected: usize,
+        found: usize,
+    },
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for CubeLutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CubeLutError::InvalidLine(line) => write!(f, "invalid LUT data on line {line}"),
+            CubeLutError::MissingSize => write!(f, "LUT is missing LUT_3D_SIZE"),
+            CubeLutError::WrongEntryCount { expected, found } => {
+                write!(f, "expected {expected} LUT entries, found {found}")
+            }
+            CubeLutError::Unsupported(feature) => write!(f, "{feature} are not supported"),
+        }
+    }
+}
+
+impl std::error::Error for CubeLutError {}
+
+impl TextAsset for CubeLut {
+    const EXTENSIONS: &'static [&'static str] = &["cube"];
+}
+
+/// Loads `.cube` files as `CubeLut` assets.
+pub type CubeLutLoader = TextAssetLoader<CubeLut>;
+
+/// Grades a `LedArea`'s output with a 3D LUT, applied after tone mapping. The area is sent
+/// ungraded until the LUT has loaded, and picks up changes when the asset is reloaded.
+#[derive(Component, Clone, Debug, Default, Deref)]
+pub struct LedLut(pub Handle<CubeLut>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A LUT of `size` entries along each axis that maps every colour to itself.
+    fn identity(size: usize) -> String {
+        let max = (size - 1) as f32;
+        let mut text = format!("LUT_3D_SIZE {size}\n");
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    text += &format!("{} {} {}\n", r as f32 / max, g as f32 / max, b as f32 / max);
+                }
+            }
+        }
+        text
+    }
+
+    fn assert_close(actual: Vec3, expected: Vec3) {
+        assert!(actual.abs_diff_eq(expected, 1e-5), "{actual} != {expected}");
+    }
+
+    #[test]
+    fn parses_the_header() {
+        let lut = format!(
+            "# Created by hand\nTITLE \"Grade\"\nDOMAIN_MIN 0 0.1 0.2\nDOMAIN_MAX 1 2 3\n{}",
+            identity(2)
+        )
+        .parse::<CubeLut>()
+        .unwrap();
+        assert_eq!(lut.title.as_deref(), Some("Grade"));
+        assert_eq!(lut.size, 2);
+        assert_eq!(lut.domain_min, Vec3::new(0.0, 0.1, 0.2));
+        assert_eq!(lut.domain_max, Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(lut.data.len(), 8);
+        // Red changes fastest
+        assert_eq!(lut.data[1], Vec3::X);
+        assert_eq!(lut.data[4], Vec3::Z);
+    }
+
+    #[test]
+    fn parses_the_input_range() {
+        let lut = format!("LUT_3D_INPUT_RANGE 0 4\n{}", identity(2))
+            .parse::<CubeLut>()
+            .unwrap();
+        assert_eq!(lut.domain_min, Vec3::ZERO);
+        assert_eq!(lut.domain_max, Vec3::splat(4.0));
+        assert_close(
+            lut.sample(Vec3::new(1.0, 2.0, 4.0)),
+            Vec3::new(0.25, 0.5, 1.0),
+        );
+    }
+
+    #[test]
+    fn identity_round_trips() {
+        let lut = identity(5).parse::<CubeLut>().unwrap();
+        for color in [
+            Vec3::ZERO,
+            Vec3::ONE,
+            Vec3::new(0.1, 0.5, 0.9),
+            Vec3::new(0.33, 0.66, 0.01),
+            Vec3::new(1.0, 0.0, 0.75),
+        ] {
+            assert_close(lut.sample(color), color);
+        }
+    }
+
+    #[test]
+    fn interpolates_trilinearly() {
+        // Only the white corner is lit
+        let mut text = "LUT_3D_SIZE 2\n".to_string();
+        text += &"0 0 0\n".repeat(7);
+        text += "1 1 1\n";
+        let lut = text.parse::<CubeLut>().unwrap();
+        assert_close(lut.sample(Vec3::splat(0.5)), Vec3::splat(0.125));
+        assert_close(lut.sample(Vec3::new(1.0, 1.0, 0.5)), Vec3::splat(0.5));
+        assert_close(lut.sample(Vec3::ONE), Vec3::ONE);
+    }
+
+    #[test]
+    fn clamps_to_the_domain() {
+        let lut = identity(3).parse::<CubeLut>().unwrap();
+        assert_close(
+            lut.sample(Vec3::new(-1.0, 2.0, 0.5)),
+            Vec3::new(0.0, 1.0, 0.5),
+        );
+    }
+
+    #[test]
+    fn reports_the_invalid_line() {
+        let text = "# A comment\n\nLUT_3D_SIZE 2\n0 0 0\n1 0\n";
+        assert!(matches!(
+            text.parse::<CubeLut>(),
+            Err(CubeLutError::InvalidLine(5))
+        ));
+        assert!(matches!(
+            "LUT_3D_INPUT_RANGE 0\n".parse::<CubeLut>(),
+            Err(CubeLutError::InvalidLine(1))
+        ));
+    }
+
+    #[test]
+    fn rejects_incomplete_luts() {
+        assert!(matches!(
+            "0 0 0\n".parse::<CubeLut>(),
+            Err(CubeLutError::MissingSize)
+        ));
+        assert!(matches!(
+            "LUT_3D_SIZE 2\n0 0 0\n".parse::<CubeLut>(),
+            Err(CubeLutError::WrongEntryCount {
+                expected: 8,
+                found: 1
+            })
+        ));
+        assert!(matches!(
+            "LUT_1D_SIZE 2\n0 0 0\n1 1 1\n".parse::<CubeLut>(),
+            Err(CubeLutError::Unsupported(_))
+        ));
+    }
+}
@@ -6,6 +6,7 @@
 @group(0) @binding(0) var<uniform> view: View;
 @group(1) @binding(0) var<uniform> material: LedMaterial;
 @group(1) @binding(1) var<storage, read> average_colors: array<vec4<f32>>;
+@group(1) @binding(2) var<storage, read> points: array<vec2<f32>>;
 
 struct LedMaterial {
     offset: u32,
@@ -13,15 +14,22 @@ struct LedMaterial {
     count: u32,
     position: vec2<f32>,
     size: vec2<f32>,
+    columns: u32,
+    rows: u32,
+    use_points: u32,
+    point_offset: u32,
+    point_radius: f32,
 }
 
 struct Vertex {
     @builtin(vertex_index) index: u32,
+    @builtin(instance_index) instance: u32,
 };
 
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(0) uv: vec2<f32>,
+    @location(1) @interpolate(flat) instance: u32,
 };
 
 @vertex
@@ -58,6 +66,12 @@ fn vertex(vertex: Vertex) -> VertexOutput {
     // Translate to the correct position
     var world_position = rotated_position + material.position;
 
+    // Point areas draw a square around each LED's point instead, cut into a circle
+    if (material.use_points != 0u) {
+        let center = points[material.point_offset + vertex.instance];
+        world_position = center + (p * 2.0 - 1.0) * material.point_radius;
+    }
+
     // Convert to clip space
     var ndc_position = vec2<f32>(
         (world_position.x - view.viewport.x) / view.viewport.z * 2.0 - 1.0,
@@ -70,6 +84,7 @@ fn vertex(vertex: Vertex) -> VertexOutput {
     var out: VertexOutput;
     out.position = clip_space_position;
     out.uv = uvs[vertex.index];
+    out.instance = vertex.instance;
     return out;
 }
 
@@ -77,6 +92,16 @@ fn vertex(vertex: Vertex) -> VertexOutput {
 fn fragment(
     mesh: VertexOutput,
 ) -> @location(0) vec4<f32> {
+    if (material.use_points != 0u) {
+        if (length(mesh.uv * 2.0 - 1.0) > 1.0) {
+            discard;
+        }
+        return point_fragment(mesh);
+    }
+    if (material.rows > 1u) {
+        return matrix_fragment(mesh.uv);
+    }
+
     let uv = mesh.uv * material.size / max(material.size.x, material.size.y);
 //    if (uv.x < 0.01 || uv.x > 0.99 || uv.y < 0.01 || uv.y > 0.99) {
 //        return vec4(1.0, 1.0, 1.0, 1.0);
@@ -92,4 +117,29 @@ fn fragment(
 
     let color = average_colors[material.offset + led_index];
     return vec4(color.xyz, 1.0);
-}
\ No newline at end of file
+}
+
+fn matrix_fragment(uv: vec2<f32>) -> vec4<f32> {
+    // Colours are stored row by row, regardless of how the matrix is wired
+    let cell = uv * vec2<f32>(f32(material.columns), f32(material.rows));
+    let column = min(u32(cell.x), material.columns - 1u);
+    let row = min(u32(cell.y), material.rows - 1u);
+    // Draw a white line on the border between cells
+    let border = cell - floor(cell);
+    if (border.x < 0.03 || border.y < 0.03) {
+        return vec4(1.0, 1.0, 1.0, 1.0);
+    }
+
+    let color = average_colors[material.offset + row * material.columns + column];
+    return vec4(color.xyz, 1.0);
+}
+
+fn point_fragment(mesh: VertexOutput) -> vec4<f32> {
+    // Draw a white outline around each LED
+    if (length(mesh.uv * 2.0 - 1.0) > 0.85) {
+        return vec4(1.0, 1.0, 1.0, 1.0);
+    }
+
+    let color = average_colors[material.offset + mesh.instance];
+    return vec4(color.xyz, 1.0);
+}
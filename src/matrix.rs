@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use bevy::render::extract_component::ExtractComponent;
+
+/// The order LEDs are wired through a matrix, starting from its `MatrixCorner`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatrixWiring {
+    /// Every row runs in the same direction.
+    #[default]
+    RowMajor,
+    /// Every column runs in the same direction.
+    ColumnMajor,
+    /// Rows alternate direction, as when a single strip is folded back and forth.
+    SerpentineRows,
+    /// Columns alternate direction.
+    SerpentineColumns,
+}
+
+/// The corner of a matrix with the first LED.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MatrixCorner {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Lays a `LedArea` out as a grid of `columns` by `rows` LEDs rather than a single strip. The
+/// area's data is flattened in wiring order.
+#[derive(Component, ExtractComponent, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LedMatrix {
+    pub columns: u32,
+    pub rows: u32,
+    pub wiring: MatrixWiring,
+    pub start: MatrixCorner,
+}
+
+impl LedMatrix {
+    pub fn new(columns: u32, rows: u32) -> Self {
+        Self {
+            columns,
+            rows,
+            wiring: MatrixWiring::default(),
+            start: MatrixCorner::default(),
+        }
+    }
+
+    pub fn wiring(mut self, wiring: MatrixWiring) -> Self {
+        self.wiring = wiring;
+        self
+    }
+
+    pub fn start(mut self, start: MatrixCorner) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// The number of LEDs in the matrix.
+    pub fn count(&self) -> u32 {
+        self.columns * self.rows
+    }
+
+    /// The column and row of the LED at `index` in wiring order.
+    pub fn position(&self, index: u32) -> UVec2 {
+        let (columns, rows) = (self.columns.max(1), self.rows.max(1));
+        let (mut column, mut row) = match self.wiring {
+            MatrixWiring::RowMajor | MatrixWiring::SerpentineRows => {
+                (index % columns, index / columns)
+            }
+            MatrixWiring::ColumnMajor | MatrixWiring::SerpentineColumns => {
+                (index / rows, index % rows)
+            }
+        };
+
+        match self.wiring {
+            MatrixWiring::SerpentineRows if row % 2 == 1 => column = columns - 1 - column,
+            MatrixWiring::SerpentineColumns if column % 2 == 1 => row = rows - 1 - row,
+            _ => {}
+        }
+
+        if matches!(
+            self.start,
+            MatrixCorner::TopRight | MatrixCorner::BottomRight
+        ) {
+            column = columns - 1 - column;
+        }
+        if matches!(
+            self.start,
+            MatrixCorner::BottomLeft | MatrixCorner::BottomRight
+        ) {
+            row = rows - 1 - row;
+        }
+        UVec2::new(column, row)
+    }
+
+    /// Reorder `LinearRgba` data sampled row by row from the top left into wiring order, or
+    /// `None` if there isn't exactly one `LinearRgba` per LED.
+    pub(crate) fn rewire(&self, data: &[f32]) -> Option<Vec<f32>> {
+        if data.len() != self.count() as usize * 4 {
+            return None;
+        }
+        let rewired = (0..self.count())
+            .flat_map(|index| {
+                let position = self.position(index);
+                let start = (position.y * self.columns + position.x) as usize * 4;
+                &data[start..start + 4]
+            })
+            .copied()
+            .collect();
+        Some(rewired)
+    }
+}
@@ -0,0 +1,271 @@
+use crate::output::{encode_output_data, OutputData};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use crossbeam_channel::{Receiver, Sender};
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+/// The TCP port used by Open Pixel Control servers.
+pub const OPC_PORT: u16 = 7890;
+
+const OPC_SET_PIXEL_COLORS: u8 = 0;
+const OPC_CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+const OPC_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+const OPC_RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct OpcPlugin;
+
+impl Plugin for OpcPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OpcClients>()
+            .init_resource::<OpcFrames>()
+            .observe(patch_opc_output)
+            .add_systems(First, send_opc_frames.after(encode_output_data));
+    }
+}
+
+/// Sends a `LedArea`'s pixel data to an Open Pixel Control server, such as a Fadecandy server.
+/// Several areas may share a channel by starting at different pixels.
+#[derive(Component, Clone, Debug)]
+pub struct OpcOutput {
+    pub server: SocketAddr,
+    /// The OPC channel, where 0 addresses every channel on the server.
+    pub channel: u8,
+    /// The index of the area's first pixel on the channel.
+    pub offset: usize,
+}
+
+impl OpcOutput {
+    pub fn new(ip: impl Into<IpAddr>) -> Self {
+        Self {
+            server: SocketAddr::new(ip.into(), OPC_PORT),
+            channel: 0,
+            offset: 0,
+        }
+    }
+
+    pub fn channel(mut self, channel: u8) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    pub fn offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+}
+
+struct OpcMessage {
+    channel: u8,
+    data: Vec<u8>,
+}
+
+/// Connections to OPC servers. Each is serviced by a background thread so that the main thread
+/// never blocks on the network.
+#[derive(Resource, Deref, DerefMut, Default)]
+struct OpcClients(HashMap<SocketAddr, Sender<OpcMessage>>);
+
+/// The RGB data for each channel of each server, gathered from the areas patched this frame.
+#[derive(Resource, Deref, DerefMut, Default)]
+struct OpcFrames(HashMap<(SocketAddr, u8), Vec<u8>>);
+
+fn patch_opc_output(
+    trigger: Trigger<OutputData>,
+    outputs_q: Query<&OpcOutput>,
+    mut frames: ResMut<OpcFrames>,
+) {
+    let Ok(output) = outputs_q.get(trigger.entity()) else {
+        return;
+    };
+
+    // The length field limits a message to whole pixels within a u16
+    const OPC_MAX_DATA: usize = u16::MAX as usize - u16::MAX as usize % 3;
+    let data = trigger.event().rgb();
+    let offset = output.offset.saturating_mul(3).min(OPC_MAX_DATA);
+    let end = (offset + data.len()).min(OPC_MAX_DATA);
+    if offset + data.len() > OPC_MAX_DATA {
+        warn_once!(
+            "OPC output for {} exceeds the channel, truncating",
+            trigger.entity()
+        );
+    }
+
+    let frame = frames.entry((output.server, output.channel)).or_default();
+    if frame.len() < end {
+        frame.resize(end, 0);
+    }
+    frame[offset..end].copy_from_slice(&data[..end - offset]);
+}
+
+fn send_opc_frames(mut frames: ResMut<OpcFrames>, mut clients: ResMut<OpcClients>) {
+    for ((server, channel), frame) in frames.iter_mut() {
+        if frame.is_empty() {
+            continue;
+        }
+        // Rebuilt from this frame's areas each frame
+        let data = std::mem::take(frame);
+        let client = clients
+            .entry(*server)
+            .or_insert_with(|| spawn_opc_client(*server));
+        let _ = client.send(OpcMessage {
+            channel: *channel,
+            data,
+        });
+    }
+}
+
+fn spawn_opc_client(server: SocketAddr) -> Sender<OpcMessage> {
+    let (s, r) = crossbeam_channel::unbounded();
+    std::thread::Builder::new()
+        .name(format!("opc-client-{server}"))
+        .spawn(move || run_opc_client(server, r))
+        .expect("Failed to spawn OPC client thread");
+    s
+}
+
+fn run_opc_client(server: SocketAddr, receiver: Receiver<OpcMessage>) {
+    let mut stream: Option<TcpStream> = None;
+    let mut last_attempt: Option<Instant> = None;
+
+    while let Ok(message) = receiver.recv() {
+        // Only the most recent frame for each channel is worth sending
+        let mut messages = vec![message];
+        for message in receiver.try_iter() {
+            messages.retain(|m| m.channel != message.channel);
+            messages.push(message);
+        }
+
+        if stream.is_none() {
+            // Drop frames while the server is down rather than hammering it with connections
+            if last_attempt.is_some_and(|last| last.elapsed() < OPC_RECONNECT_INTERVAL) {
+                continue;
+            }
+            last_attempt = Some(Instant::now());
+
+            match TcpStream::connect_timeout(&server, OPC_CONNECT_TIMEOUT) {
+                Ok(s) => {
+                    let _ = s.set_nodelay(true);
+                    let _ = s.set_write_timeout(Some(OPC_WRITE_TIMEOUT));
+                    info!("Connected to OPC server {server}");
+                    stream = Some(s);
+                }
+                Err(err) => {
+                    warn!("Failed to connect to OPC server {server}: {err}");
+                    continue;
+                }
+            }
+        }
+
+        let Some(s) = stream.as_mut() else {
+            continue;
+        };
+        for message in messages {
+            let mut packet = Vec::with_capacity(4 + message.data.len());
+            packet.extend_from_slice(&[message.channel, OPC_SET_PIXEL_COLORS]);
+            packet.extend_from_slice(&(message.data.len() as u16).to_be_bytes());
+            packet.extend_from_slice(&message.data);
+            if let Err(err) = s.write_all(&packet) {
+                warn!("Lost connection to OPC server {server}: {err}");
+                stream = None;
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OutputDepth, PixelFormat};
+    use bevy::ecs::system::RunSystemOnce;
+    use std::io::{ErrorKind, Read};
+    use std::net::{Ipv4Addr, TcpListener};
+    use std::thread;
+
+    fn rgb(data: &[u8]) -> OutputData {
+        OutputData {
+            data: data.to_vec(),
+            bytes_per_pixel: 3,
+            depth: OutputDepth::Eight,
+            format: PixelFormat::RGB,
+        }
+    }
+
+    fn read_packet(stream: &mut TcpStream) -> [u8; 10] {
+        stream.set_nonblocking(false).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut packet = [0; 10];
+        stream.read_exact(&mut packet).unwrap();
+        packet
+    }
+
+    #[test]
+    fn frames_pixels_and_reconnects_after_the_server_restarts() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let server = listener.local_addr().unwrap();
+        let client = spawn_opc_client(server);
+        let send = || {
+            client
+                .send(OpcMessage {
+                    channel: 1,
+                    data: vec![1, 2, 3, 4, 5, 6],
+                })
+                .unwrap()
+        };
+
+        // The channel, the command, the big-endian length and then the RGB data
+        send();
+        let (mut stream, _) = listener.accept().unwrap();
+        assert_eq!(read_packet(&mut stream), [1, 0, 0, 6, 1, 2, 3, 4, 5, 6]);
+
+        // Take the server down, and bring it back on the same port
+        drop(stream);
+        drop(listener);
+        let listener = TcpListener::bind(server).unwrap();
+        listener.set_nonblocking(true).unwrap();
+
+        // Frames keep coming, so the client notices the lost connection and reconnects
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let mut stream = loop {
+            send();
+            match listener.accept() {
+                Ok((stream, _)) => break stream,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => {
+                    assert!(Instant::now() < deadline, "client didn't reconnect");
+                    thread::sleep(Duration::from_millis(20));
+                }
+                Err(err) => panic!("failed to accept OPC client: {err}"),
+            }
+        };
+        assert_eq!(read_packet(&mut stream), [1, 0, 0, 6, 1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn areas_sharing_a_channel_are_sent_as_one_frame() {
+        let server = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), OPC_PORT);
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let mut world = World::new();
+        world.init_resource::<OpcFrames>();
+        world.insert_resource(OpcClients([(server, sender)].into_iter().collect()));
+        world.observe(patch_opc_output);
+
+        let output = OpcOutput::new(Ipv4Addr::LOCALHOST).channel(1);
+        let first = world.spawn(output.clone()).id();
+        let second = world.spawn(output.offset(2)).id();
+        world.trigger_targets(rgb(&[1, 2, 3]), first);
+        world.trigger_targets(rgb(&[4, 5, 6]), second);
+        world.run_system_once(send_opc_frames);
+
+        let message = receiver.try_recv().unwrap();
+        assert_eq!(message.channel, 1);
+        assert_eq!(message.data, [1, 2, 3, 0, 0, 0, 4, 5, 6]);
+        assert!(receiver.try_recv().is_err());
+
+        // Nothing is sent until the areas are patched again
+        world.run_system_once(send_opc_frames);
+        assert!(receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,651 @@
+use crate::calibration::{CalibrationProfiles, ColorCalibration, FixtureType};
+use crate::lut::{CubeLut, CubeLutLoader, LedLut};
+use crate::pixel::{
+    LedTonemapping, Pixel, PixelChannel, PixelFormat, TransferCurve, WhiteBalance, WhiteExtraction,
+};
+use crate::power::{budget_scale, PowerBudget, PowerDraw, PowerModel, POWER_DRAW};
+use crate::{send_led_data, LedArea, ReceivedData};
+use bevy::diagnostic::Diagnostics;
+use bevy::ecs::entity::EntityHashMap;
+use bevy::ecs::query::QueryData;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::ops::Range;
+
+/// The number of channels in a single DMX universe.
+pub const DMX_UNIVERSE_SIZE: usize = 512;
+
+/// The DMX512 null start code.
+pub(crate) const DMX_START_CODE: u8 = 0;
+
+pub struct OutputPlugin;
+
+impl Plugin for OutputPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PendingOutputData>()
+            .init_resource::<PixelmapMaster>()
+            .init_resource::<CalibrationProfiles>()
+            .init_asset::<CubeLut>()
+            .init_asset_loader::<CubeLutLoader>()
+            .observe(collect_output_data)
+            .add_systems(First, encode_output_data.after(send_led_data));
+    }
+}
+
+/// Quantized channel data for a `LedArea`, ready to be sent by an output backend.
+#[derive(Event, Debug)]
+pub struct OutputData {
+    /// The channel values for every pixel in the area, in output order.
+    pub data: Vec<u8>,
+    /// The number of bytes that make up a single pixel.
+    pub bytes_per_pixel: usize,
+    /// The number of bytes sent for each channel.
+    pub depth: OutputDepth,
+    /// The channels of each pixel, in output order.
+    pub format: PixelFormat,
+}
+
+impl OutputData {
+    /// The number of channels in a single pixel, whatever their depth.
+    pub fn channels(&self) -> usize {
+        self.format.channels()
+    }
+
+    /// A single byte per channel, keeping only the coarse byte of 16-bit channels, for
+    /// protocols that can't carry them.
+    pub fn eight_bit(&self) -> Vec<u8> {
+        match self.depth {
+            OutputDepth::Eight => self.data.clone(),
+            OutputDepth::Sixteen => self.data.iter().step_by(2).copied().collect(),
+        }
+    }
+
+    /// Every pixel as 8-bit red, green and blue, for protocols that only carry RGB. White and
+    /// intensity channels are added back into all three, so extracted white isn't lost.
+    pub fn rgb(&self) -> Vec<u8> {
+        let channels = self.channels();
+        if channels == 0 {
+            return Vec::new();
+        }
+
+        self.eight_bit()
+            .chunks(channels)
+            .flat_map(|pixel| {
+                let mut rgb = [0u8; 3];
+                for (channel, value) in self.format.0.iter().zip(pixel) {
+                    let targets = match channel {
+                        PixelChannel::Red => 0..1,
+                        PixelChannel::Green => 1..2,
+                        PixelChannel::Blue => 2..3,
+                        PixelChannel::White | PixelChannel::WarmWhite | PixelChannel::Intensity => {
+                            0..3
+                        }
+                    };
+                    rgb[targets]
+                        .iter_mut()
+                        .for_each(|v| *v = v.saturating_add(*value));
+                }
+                rgb
+            })
+            .collect()
+    }
+}
+
+/// The resolution of each channel sent by a `LedArea`.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputDepth {
+    /// A single byte per channel.
+    #[default]
+    Eight,
+    /// A big-endian coarse/fine pair of bytes per channel, for fixtures with 16-bit channels.
+    Sixteen,
+}
+
+impl OutputDepth {
+    /// The number of bytes sent for each channel.
+    pub fn bytes(&self) -> usize {
+        match self {
+            OutputDepth::Eight => 1,
+            OutputDepth::Sixteen => 2,
+        }
+    }
+}
+
+/// The master controls for every pixelmap, applied on top of each area's `Dimmer`.
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct PixelmapMaster {
+    /// The intensity of every area, from 0 to 1.
+    pub intensity: f32,
+    /// Send zeros to every area, regardless of what is sampled. Output keeps running so that
+    /// receivers hold black rather than timing out into their own effects.
+    pub blackout: bool,
+}
+
+impl Default for PixelmapMaster {
+    fn default() -> Self {
+        Self {
+            intensity: 1.0,
+            blackout: false,
+        }
+    }
+}
+
+/// The intensity of a `LedArea`, from 0 to 1, applied to linear light after sampling.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Deref, DerefMut)]
+pub struct Dimmer(pub f32);
+
+impl Default for Dimmer {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// Temporal dithering for 8-bit output. The quantization error of every channel is carried
+/// into the next frame, so that slow, dark fades are smoothed over time rather than stepping.
+#[derive(Component, Clone, Debug, Default)]
+pub struct TemporalDither {
+    count: u32,
+    error: Vec<f32>,
+}
+
+impl TemporalDither {
+    fn quantize(&mut self, count: u32, values: &[f32]) -> Vec<u8> {
+        // The accumulated error is meaningless once the LEDs it belonged to have changed
+        if self.count != count || self.error.len() != values.len() {
+            self.count = count;
+            self.error = vec![0.0; values.len()];
+        }
+
+        values
+            .iter()
+            .zip(self.error.iter_mut())
+            .map(|(value, error)| {
+                let target = value.clamp(0.0, 1.0) * 255.0 + *error;
+                let quantized = target.round().clamp(0.0, 255.0);
+                *error = target - quantized;
+                quantized as u8
+            })
+            .collect()
+    }
+}
+
+/// Whether a pixel's channels may be split across a universe boundary.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PixelStraddle {
+    /// Move a pixel to the next universe when it doesn't fit in the current one.
+    #[default]
+    Deny,
+    /// Fill every universe completely, splitting pixels across the boundary.
+    Allow,
+}
+
+/// Where a `LedArea`'s channels start and how they flow across consecutive universes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DmxPatch {
+    /// The 1-based channel in the first universe.
+    pub start_channel: u16,
+    /// Whether a pixel may straddle two universes.
+    pub straddle: PixelStraddle,
+}
+
+impl Default for DmxPatch {
+    fn default() -> Self {
+        Self {
+            start_channel: 1,
+            straddle: PixelStraddle::Deny,
+        }
+    }
+}
+
+/// A run of an area's channels to be written into a single universe.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UniverseChunk {
+    /// The universe, relative to the first universe of the patch.
+    pub universe: u16,
+    /// The 0-based channel offset within the universe.
+    pub offset: usize,
+    /// The range of the area's data to write.
+    pub range: Range<usize>,
+}
+
+impl DmxPatch {
+    /// The patch starting `channels` after this one, with channels running on contiguously
+    /// into the following universes, and the number of universes it has moved on by.
+    pub fn advance(&self, channels: usize) -> (u16, DmxPatch) {
+        let channel = self.start_channel.clamp(1, DMX_UNIVERSE_SIZE as u16) as usize - 1 + channels;
+        let patch = DmxPatch {
+            start_channel: (channel % DMX_UNIVERSE_SIZE) as u16 + 1,
+            straddle: self.straddle,
+        };
+        ((channel / DMX_UNIVERSE_SIZE) as u16, patch)
+    }
+
+    /// Split `len` channels of pixel data across as many universes as necessary.
+    pub fn split(&self, len: usize, bytes_per_pixel: usize) -> Vec<UniverseChunk> {
+        let mut chunks = Vec::new();
+        let mut universe = 0;
+        let mut offset = self.start_channel.clamp(1, DMX_UNIVERSE_SIZE as u16) as usize - 1;
+        let mut start = 0;
+        while start < len {
+            let mut available = DMX_UNIVERSE_SIZE - offset;
+            if self.straddle == PixelStraddle::Deny
+                && bytes_per_pixel > 0
+                && bytes_per_pixel <= DMX_UNIVERSE_SIZE
+            {
+                // Only whole pixels fit, the rest of the universe is left untouched
+                available -= available % bytes_per_pixel;
+            }
+
+            if available > 0 {
+                let end = (start + available).min(len);
+                chunks.push(UniverseChunk {
+                    universe,
+                    offset,
+                    range: start..end,
+                });
+                start = end;
+            }
+
+            universe += 1;
+            offset = 0;
+        }
+        chunks
+    }
+}
+
+/// Pixel data received this frame that has not yet been encoded for output.
+#[derive(Resource, Deref, DerefMut, Default)]
+struct PendingOutputData(EntityHashMap<Vec<f32>>);
+
+fn collect_output_data(trigger: Trigger<ReceivedData>, mut pending: ResMut<PendingOutputData>) {
+    pending.insert(trigger.entity(), trigger.event().0.clone());
+}
+
+/// The components of a `LedArea` that control how its pixels are encoded.
+#[derive(QueryData)]
+#[query_data(mutable)]
+struct OutputSettings {
+    area: &'static LedArea,
+    format: Option<&'static PixelFormat>,
+    tonemapping: Option<&'static LedTonemapping>,
+    lut: Option<&'static LedLut>,
+    white_extraction: Option<&'static WhiteExtraction>,
+    white_balance: Option<&'static WhiteBalance>,
+    transfer_curve: Option<&'static TransferCurve>,
+    depth: Option<&'static OutputDepth>,
+    dither: Option<&'static mut TemporalDither>,
+    dimmer: Option<&'static Dimmer>,
+    calibration: Option<&'static ColorCalibration>,
+    fixture_type: Option<&'static FixtureType>,
+    power_model: Option<&'static PowerModel>,
+}
+
+/// An area's channel values for this frame, before power limiting and quantization.
+struct EncodedArea {
+    entity: Entity,
+    values: Vec<f32>,
+    power: Option<AreaPower>,
+}
+
+struct AreaPower {
+    draw: f32,
+    limit: Option<f32>,
+    group: Option<String>,
+}
+
+pub(crate) fn encode_output_data(
+    mut commands: Commands,
+    mut pending: ResMut<PendingOutputData>,
+    mut settings_q: Query<OutputSettings>,
+    master: Res<PixelmapMaster>,
+    luts: Res<Assets<CubeLut>>,
+    profiles: Res<CalibrationProfiles>,
+    budget: Res<PowerBudget>,
+    mut diagnostics: Diagnostics,
+) {
+    let mut areas = Vec::new();
+    for (entity, data) in pending.drain() {
+        let Ok(settings) = settings_q.get(entity) else {
+            continue;
+        };
+        let format = settings.format.copied().unwrap_or_default();
+
+        // The readback holds a `LinearRgba` per LED, alpha is never sent
+        let mut pixels = data
+            .chunks_exact(4)
+            .map(|rgba| Pixel::rgb(rgba[0], rgba[1], rgba[2]))
+            .collect::<Vec<_>>();
+
+        if let Some(tonemapping) = settings.tonemapping {
+            pixels.iter_mut().for_each(|pixel| tonemapping.apply(pixel));
+        }
+
+        if let Some(lut) = settings.lut.and_then(|lut| luts.get(&lut.0)) {
+            pixels.iter_mut().for_each(|pixel| lut.apply(pixel));
+        }
+
+        if let Some(white_extraction) = settings.white_extraction {
+            if format.has(PixelChannel::White) {
+                let white_balance = format
+                    .has(PixelChannel::WarmWhite)
+                    .then(|| settings.white_balance.copied().unwrap_or_default());
+                pixels.iter_mut().for_each(|pixel| {
+                    let color = pixel.color();
+                    white_extraction.apply(pixel);
+                    if let Some(white_balance) = white_balance {
+                        white_balance.split(color, pixel);
+                    }
+                });
+            }
+        }
+
+        let calibration = settings.calibration.or_else(|| {
+            let fixture_type = settings.fixture_type?;
+            let profile = profiles.get(fixture_type);
+            if profile.is_none() {
+                warn_once!("No calibration profile for fixture type {}", **fixture_type);
+            }
+            profile
+        });
+        if let Some(calibration) = calibration {
+            pixels.iter_mut().for_each(|pixel| calibration.apply(pixel));
+        }
+
+        let mut values = pixels
+            .iter()
+            .flat_map(|pixel| format.0.iter().map(|channel| pixel.channel(*channel)))
+            .collect::<Vec<_>>();
+
+        if let Some(calibration) = calibration {
+            for pixel in values.chunks_exact_mut(format.channels().max(1)) {
+                for (value, channel) in pixel.iter_mut().zip(format.0) {
+                    *value = calibration.trim(*channel, *value);
+                }
+            }
+        }
+
+        let intensity = master.intensity * settings.dimmer.map_or(1.0, |dimmer| dimmer.0);
+        if intensity != 1.0 {
+            let intensity = intensity.max(0.0);
+            values.iter_mut().for_each(|value| *value *= intensity);
+        }
+
+        if let Some(transfer_curve) = settings.transfer_curve {
+            values
+                .iter_mut()
+                .for_each(|value| *value = transfer_curve.apply(*value));
+        }
+
+        // Curves and offsets may lift black, so blackout is applied last
+        if master.blackout {
+            values.fill(0.0);
+        }
+
+        let power = settings.power_model.map(|model| AreaPower {
+            draw: model.estimate(&values),
+            limit: model.limit,
+            group: model.group.clone(),
+        });
+        areas.push(EncodedArea {
+            entity,
+            values,
+            power,
+        });
+    }
+
+    // Supplies are shared between areas, so the whole frame's draw is needed before any area
+    // can be scaled
+    let mut total_draw = 0.0;
+    let mut group_draw = HashMap::<&str, f32>::new();
+    for power in areas.iter().filter_map(|area| area.power.as_ref()) {
+        total_draw += power.draw;
+        if let Some(group) = &power.group {
+            *group_draw.entry(group.as_str()).or_default() += power.draw;
+        }
+    }
+    let global_scale = budget
+        .global
+        .map_or(1.0, |limit| budget_scale(total_draw, limit));
+    let scales = areas
+        .iter()
+        .map(|area| {
+            let power = area.power.as_ref()?;
+            let mut scale = global_scale;
+            if let Some(limit) = power.limit {
+                scale = scale.min(budget_scale(power.draw, limit));
+            }
+            if let Some(group) = &power.group {
+                if let Some(limit) = budget.groups.get(group) {
+                    scale = scale.min(budget_scale(group_draw[group.as_str()], *limit));
+                }
+            }
+            Some(scale)
+        })
+        .collect::<Vec<_>>();
+
+    let mut limited_draw = 0.0;
+    for (area, scale) in areas.into_iter().zip(scales) {
+        let Ok(mut settings) = settings_q.get_mut(area.entity) else {
+            continue;
+        };
+        let format = settings.format.copied().unwrap_or_default();
+        let depth = settings.depth.copied().unwrap_or_default();
+        let mut values = area.values;
+
+        if let (Some(power), Some(scale)) = (area.power, scale) {
+            values
+                .iter_mut()
+                .for_each(|value| *value = value.clamp(0.0, 1.0) * scale);
+            limited_draw += power.draw * scale;
+            commands.entity(area.entity).insert(PowerDraw {
+                requested: power.draw,
+                scale,
+            });
+        }
+
+        let data = match depth {
+            OutputDepth::Eight => match settings.dither.as_mut() {
+                // Carried error would leak through blackout as stray values
+                Some(dither) if !master.blackout => dither.quantize(settings.area.count, &values),
+                _ => f32_vec_to_u8_vec(values),
+            },
+            OutputDepth::Sixteen => values
+                .into_iter()
+                .flat_map(|value| f32_to_u16(value).to_be_bytes())
+                .collect(),
+        };
+
+        commands.trigger_targets(
+            OutputData {
+                data,
+                bytes_per_pixel: format.channels() * depth.bytes(),
+                depth,
+                format,
+            },
+            area.entity,
+        );
+    }
+
+    diagnostics.add_measurement(&POWER_DRAW, || limited_draw);
+}
+
+fn f32_to_u8(value: f32) -> u8 {
+    // Clamp the value to the range [0.0, 1.0] to ensure valid u8 conversion
+    let clamped_value = value.clamp(0.0, 1.0);
+    // Scale the clamped value to the range [0, 255] and cast to u8
+    (clamped_value * 255.0).round() as u8
+}
+
+fn f32_to_u16(value: f32) -> u16 {
+    (value.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16
+}
+
+fn f32_vec_to_u8_vec(values: Vec<f32>) -> Vec<u8> {
+    values.iter().map(|&v| f32_to_u8(v)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patch(start_channel: u16, straddle: PixelStraddle) -> DmxPatch {
+        DmxPatch {
+            start_channel,
+            straddle,
+        }
+    }
+
+    fn chunk(universe: u16, offset: usize, range: Range<usize>) -> UniverseChunk {
+        UniverseChunk {
+            universe,
+            offset,
+            range,
+        }
+    }
+
+    fn output(format: PixelFormat, data: Vec<u8>) -> OutputData {
+        OutputData {
+            data,
+            bytes_per_pixel: format.channels(),
+            depth: OutputDepth::Eight,
+            format,
+        }
+    }
+
+    #[test]
+    fn rgb_keeps_extracted_white() {
+        for extraction in [
+            WhiteExtraction::MinSubtract,
+            WhiteExtraction::kelvin(6500.0),
+        ] {
+            for format in [PixelFormat::RGBW, PixelFormat::GRBW, PixelFormat::RGBWW] {
+                let mut pixel = Pixel::rgb(1.0, 1.0, 1.0);
+                extraction.apply(&mut pixel);
+                let data = format
+                    .0
+                    .iter()
+                    .map(|channel| f32_to_u8(pixel.channel(*channel)))
+                    .collect();
+                assert_eq!(output(format, data).rgb(), vec![255, 255, 255]);
+            }
+        }
+    }
+
+    #[test]
+    fn rgb_follows_the_channel_order() {
+        let data = output(PixelFormat::GRB, vec![1, 2, 3, 4, 5, 6]);
+        assert_eq!(data.rgb(), vec![2, 1, 3, 5, 4, 6]);
+        let data = output(PixelFormat::SINGLE, vec![7]);
+        assert_eq!(data.rgb(), vec![7, 7, 7]);
+    }
+
+    #[test]
+    fn rgb_of_sixteen_bit_data_is_coarse() {
+        let data = OutputData {
+            data: vec![0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc],
+            bytes_per_pixel: 6,
+            depth: OutputDepth::Sixteen,
+            format: PixelFormat::RGB,
+        };
+        assert_eq!(data.rgb(), vec![0x12, 0x56, 0x9a]);
+    }
+
+    #[test]
+    fn split_fits_in_one_universe() {
+        let chunks = DmxPatch::default().split(36, 3);
+        assert_eq!(chunks, vec![chunk(0, 0, 0..36)]);
+    }
+
+    #[test]
+    fn split_deny_keeps_pixels_whole() {
+        let chunks = patch(1, PixelStraddle::Deny).split(600, 3);
+        assert_eq!(chunks, vec![chunk(0, 0, 0..510), chunk(1, 0, 510..600)]);
+    }
+
+    #[test]
+    fn split_allow_fills_universes() {
+        let chunks = patch(1, PixelStraddle::Allow).split(600, 3);
+        assert_eq!(chunks, vec![chunk(0, 0, 0..512), chunk(1, 0, 512..600)]);
+    }
+
+    #[test]
+    fn split_deny_skips_a_universe_without_room_for_a_pixel() {
+        let chunks = patch(511, PixelStraddle::Deny).split(9, 3);
+        assert_eq!(chunks, vec![chunk(1, 0, 0..9)]);
+    }
+
+    #[test]
+    fn split_allow_straddles_from_the_start_channel() {
+        let chunks = patch(511, PixelStraddle::Allow).split(9, 3);
+        assert_eq!(chunks, vec![chunk(0, 510, 0..2), chunk(1, 0, 2..9)]);
+    }
+
+    #[test]
+    fn split_offsets_by_the_start_channel() {
+        let chunks = patch(100, PixelStraddle::Deny).split(12, 4);
+        assert_eq!(chunks, vec![chunk(0, 99, 0..12)]);
+    }
+
+    #[test]
+    fn split_clamps_start_channel_zero() {
+        assert_eq!(
+            patch(0, PixelStraddle::Deny).split(3, 3),
+            vec![chunk(0, 0, 0..3)]
+        );
+    }
+
+    #[test]
+    fn split_spans_many_universes() {
+        let chunks = patch(1, PixelStraddle::Deny).split(170 * 3 * 3 + 3, 3);
+        assert_eq!(
+            chunks,
+            vec![
+                chunk(0, 0, 0..510),
+                chunk(1, 0, 510..1020),
+                chunk(2, 0, 1020..1530),
+                chunk(3, 0, 1530..1533),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_pixels_wider_than_a_universe_straddle() {
+        let chunks = patch(1, PixelStraddle::Deny).split(1200, 600);
+        assert_eq!(
+            chunks,
+            vec![
+                chunk(0, 0, 0..512),
+                chunk(1, 0, 512..1024),
+                chunk(2, 0, 1024..1200),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_nothing() {
+        assert!(DmxPatch::default().split(0, 3).is_empty());
+    }
+
+    #[test]
+    fn advance_within_a_universe() {
+        let (universes, advanced) = patch(10, PixelStraddle::Allow).advance(30);
+        assert_eq!(universes, 0);
+        assert_eq!(advanced, patch(40, PixelStraddle::Allow));
+    }
+
+    #[test]
+    fn advance_into_following_universes() {
+        assert_eq!(
+            DmxPatch::default().advance(512),
+            (1, patch(1, PixelStraddle::Deny))
+        );
+        assert_eq!(
+            patch(511, PixelStraddle::Deny).advance(3),
+            (1, patch(2, PixelStraddle::Deny))
+        );
+        assert_eq!(
+            DmxPatch::default().advance(512 * 3 + 5),
+            (3, patch(6, PixelStraddle::Deny))
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{LedArea, LedPoints};
+use bevy::prelude::*;
+
+/// The number of line segments each Bézier segment is flattened into.
+const BEZIER_STEPS: usize = 32;
+
+pub struct PathPlugin;
+
+impl Plugin for PathPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, update_path_points);
+    }
+}
+
+/// How a path's control points are joined.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathKind {
+    /// Straight lines between consecutive points.
+    #[default]
+    Polyline,
+    /// Cubic Bézier segments. Every segment is an on-curve point followed by two off-curve
+    /// handles, and the path ends on an on-curve point.
+    CubicBezier,
+}
+
+/// Lays a `LedArea`'s LEDs out evenly along a path rather than a straight strip.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct LedPath {
+    pub kind: PathKind,
+    /// The control points in screen pixels, like `LedArea::position`.
+    pub control_points: Vec<Vec2>,
+    /// The radius sampled around each LED, in screen pixels.
+    pub footprint: f32,
+}
+
+impl LedPath {
+    pub fn polyline(control_points: impl IntoIterator<Item = Vec2>) -> Self {
+        Self {
+            kind: PathKind::Polyline,
+            control_points: control_points.into_iter().collect(),
+            footprint: 5.0,
+        }
+    }
+
+    pub fn cubic_bezier(control_points: impl IntoIterator<Item = Vec2>) -> Self {
+        Self {
+            kind: PathKind::CubicBezier,
+            ..Self::polyline(control_points)
+        }
+    }
+
+    pub fn footprint(mut self, footprint: f32) -> Self {
+        self.footprint = footprint;
+        self
+    }
+
+    /// The path as a series of straight lines.
+    pub fn flatten(&self) -> Vec<Vec2> {
+        match self.kind {
+            PathKind::Polyline => self.control_points.clone(),
+            PathKind::CubicBezier => {
+                let Some(first) = self.control_points.first() else {
+                    return Vec::new();
+                };
+
+                let mut points = vec![*first];
+                for segment in self.control_points.windows(4).step_by(3) {
+                    points.extend(
+                        (1..=BEZIER_STEPS).map(|i| cubic(segment, i as f32 / BEZIER_STEPS as f32)),
+                    );
+                }
+                points
+            }
+        }
+    }
+
+    /// `count` points spaced evenly along the path, each at the centre of an equal share of
+    /// its length.
+    pub fn sample(&self, count: u32) -> Vec<Vec2> {
+        spaced_along(&self.flatten(), count)
+    }
+}
+
+fn cubic(p: &[Vec2], t: f32) -> Vec2 {
+    let u = 1.0 - t;
+    p[0] * u * u * u + p[1] * 3.0 * u * u * t + p[2] * 3.0 * u * t * t + p[3] * t * t * t
+}
+
+/// `count` points spaced evenly along a polyline, each at the centre of an equal share of its
+/// length.
+pub(crate) fn spaced_along(polyline: &[Vec2], count: u32) -> Vec<Vec2> {
+    let Some(first) = polyline.first() else {
+        return Vec::new();
+    };
+    let length = polyline
+        .windows(2)
+        .map(|w| w[0].distance(w[1]))
+        .sum::<f32>();
+    let mut segments = polyline.windows(2);
+    let (Some(mut segment), true) = (segments.next(), length > 0.0) else {
+        return vec![*first; count as usize];
+    };
+
+    let mut points = Vec::with_capacity(count as usize);
+    let mut segment_start = 0.0;
+    for i in 0..count {
+        let target = (i as f32 + 0.5) / count as f32 * length;
+        let mut segment_length = segment[0].distance(segment[1]);
+        while segment_start + segment_length < target {
+            let Some(next) = segments.next() else {
+                break;
+            };
+            segment_start += segment_length;
+            segment = next;
+            segment_length = segment[0].distance(segment[1]);
+        }
+
+        let t = if segment_length > 0.0 {
+            ((target - segment_start) / segment_length).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        points.push(segment[0].lerp(segment[1], t));
+    }
+    points
+}
+
+fn update_path_points(
+    mut commands: Commands,
+    paths_q: Query<(Entity, &LedArea, &LedPath), Or<(Changed<LedArea>, Changed<LedPath>)>>,
+) {
+    for (entity, area, path) in paths_q.iter() {
+        commands.entity(entity).insert(LedPoints {
+            points: path.sample(area.count),
+            radius: path.footprint,
+        });
+    }
+}
@@ -0,0 +1,637 @@
+use bevy::prelude::*;
+
+/// A single channel of a pixel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PixelChannel {
+    Red,
+    Green,
+    Blue,
+    White,
+    WarmWhite,
+    /// The luminance of the pixel, for single colour fixtures such as dimmers.
+    Intensity,
+}
+
+/// The channels sent for each pixel of a `LedArea`, in the order the fixture expects them.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PixelFormat(pub &'static [PixelChannel]);
+
+impl PixelFormat {
+    pub const RGB: Self = Self(&[PixelChannel::Red, PixelChannel::Green, PixelChannel::Blue]);
+    pub const GRB: Self = Self(&[PixelChannel::Green, PixelChannel::Red, PixelChannel::Blue]);
+    pub const BGR: Self = Self(&[PixelChannel::Blue, PixelChannel::Green, PixelChannel::Red]);
+    pub const RGBW: Self = Self(&[
+        PixelChannel::Red,
+        PixelChannel::Green,
+        PixelChannel::Blue,
+        PixelChannel::White,
+    ]);
+    pub const GRBW: Self = Self(&[
+        PixelChannel::Green,
+        PixelChannel::Red,
+        PixelChannel::Blue,
+        PixelChannel::White,
+    ]);
+    pub const RGBWW: Self = Self(&[
+        PixelChannel::Red,
+        PixelChannel::Green,
+        PixelChannel::Blue,
+        PixelChannel::White,
+        PixelChannel::WarmWhite,
+    ]);
+    pub const SINGLE: Self = Self(&[PixelChannel::Intensity]);
+
+    /// The number of channels per pixel.
+    pub fn channels(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether the format has a channel.
+    pub fn has(&self, channel: PixelChannel) -> bool {
+        self.0.contains(&channel)
+    }
+}
+
+impl Default for PixelFormat {
+    fn default() -> Self {
+        Self::RGB
+    }
+}
+
+/// The value of every channel of a pixel, before it is ordered into a `PixelFormat`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Pixel {
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+    pub white: f32,
+    pub warm_white: f32,
+}
+
+impl Pixel {
+    pub fn rgb(red: f32, green: f32, blue: f32) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+            ..default()
+        }
+    }
+
+    pub fn color(&self) -> Vec3 {
+        Vec3::new(self.red, self.green, self.blue)
+    }
+
+    pub fn set_color(&mut self, color: Vec3) {
+        self.red = color.x;
+        self.green = color.y;
+        self.blue = color.z;
+    }
+
+    pub fn channel(&self, channel: PixelChannel) -> f32 {
+        match channel {
+            PixelChannel::Red => self.red,
+            PixelChannel::Green => self.green,
+            PixelChannel::Blue => self.blue,
+            PixelChannel::White => self.white,
+            PixelChannel::WarmWhite => self.warm_white,
+            PixelChannel::Intensity => {
+                0.2126 * self.red
+                    + 0.7152 * self.green
+                    + 0.0722 * self.blue
+                    + self.white
+                    + self.warm_white
+            }
+        }
+    }
+}
+
+/// How the white channel of a pixel is derived from the sampled colour. Only applies to formats
+/// with a white channel, which otherwise stays dark. Formats with a warm white channel share the
+/// white between both by their `WhiteBalance`.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub enum WhiteExtraction {
+    /// Move the part of the colour common to red, green and blue into white.
+    MinSubtract,
+    /// Move as much of the colour into white as the white LED can reproduce, given its measured
+    /// CIE 1931 xy chromaticity.
+    ColorTemperature { chromaticity: Vec2 },
+    /// Add the part of the colour common to red, green and blue to white, while keeping red,
+    /// green and blue at full for extra brightness.
+    WhiteBoost,
+}
+
+impl WhiteExtraction {
+    /// Colour temperature aware extraction for a white LED of the given correlated colour
+    /// temperature, between 1667K and 25000K.
+    pub fn kelvin(kelvin: f32) -> Self {
+        let t = kelvin.clamp(1667.0, 25000.0) as f64;
+        let x = if t <= 4000.0 {
+            -0.2661239e9 / t.powi(3) - 0.2343589e6 / t.powi(2) + 0.8776956e3 / t + 0.179910
+        } else {
+            -3.0258469e9 / t.powi(3) + 2.1070379e6 / t.powi(2) + 0.2226347e3 / t + 0.240390
+        };
+        let y = if t <= 2222.0 {
+            -1.1063814 * x.powi(3) - 1.34811020 * x.powi(2) + 2.18555832 * x - 0.20219683
+        } else if t <= 4000.0 {
+            -0.9549476 * x.powi(3) - 1.37418593 * x.powi(2) + 2.09137015 * x - 0.16748867
+        } else {
+            3.0817580 * x.powi(3) - 5.87338670 * x.powi(2) + 3.75112997 * x - 0.37001483
+        };
+        Self::ColorTemperature {
+            chromaticity: Vec2::new(x as f32, y as f32),
+        }
+    }
+
+    pub(crate) fn apply(&self, pixel: &mut Pixel) {
+        match self {
+            WhiteExtraction::MinSubtract => {
+                let white = pixel.red.min(pixel.green).min(pixel.blue).max(0.0);
+                pixel.red -= white;
+                pixel.green -= white;
+                pixel.blue -= white;
+                pixel.white += white;
+            }
+            WhiteExtraction::ColorTemperature { chromaticity } => {
+                let led = white_led_rgb(*chromaticity);
+                // The most white we can add without any channel going negative
+                let white = [
+                    (pixel.red, led.x),
+                    (pixel.green, led.y),
+                    (pixel.blue, led.z),
+                ]
+                .into_iter()
+                .filter(|(_, led)| *led > 0.0)
+                .map(|(value, led)| value / led)
+                .fold(f32::INFINITY, f32::min)
+                .max(0.0);
+                if !white.is_finite() {
+                    return;
+                }
+
+                pixel.red -= white * led.x;
+                pixel.green -= white * led.y;
+                pixel.blue -= white * led.z;
+                pixel.white += white;
+            }
+            WhiteExtraction::WhiteBoost => {
+                pixel.white += pixel.red.min(pixel.green).min(pixel.blue).max(0.0);
+            }
+        }
+    }
+}
+
+/// The colour temperatures of the cool and warm white LEDs of RGBWW pixels. The white taken out
+/// by `WhiteExtraction` is shared between them by the colour temperature of the sampled colour,
+/// so warm colours are lit by the warm LED and cool colours by the cool one.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct WhiteBalance {
+    /// The colour temperature of the `White` channel's LED, in kelvin.
+    pub cool: f32,
+    /// The colour temperature of the `WarmWhite` channel's LED, in kelvin.
+    pub warm: f32,
+}
+
+impl Default for WhiteBalance {
+    fn default() -> Self {
+        Self {
+            cool: 6500.0,
+            warm: 2700.0,
+        }
+    }
+}
+
+impl WhiteBalance {
+    pub fn new(cool: f32, warm: f32) -> Self {
+        Self { cool, warm }
+    }
+
+    /// Move the share of the pixel's white that `color` calls for into warm white.
+    pub(crate) fn split(&self, color: Vec3, pixel: &mut Pixel) {
+        let Some(kelvin) = correlated_color_temperature(color) else {
+            return;
+        };
+        // Interpolate in mireds, which are far closer to perceptually even than kelvin
+        let cool = 1e6 / self.cool.max(1.0);
+        let warm = 1e6 / self.warm.max(1.0);
+        let warmth = if warm > cool {
+            ((1e6 / kelvin - cool) / (warm - cool)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        pixel.warm_white += pixel.white * warmth;
+        pixel.white *= 1.0 - warmth;
+    }
+}
+
+/// McCamy's approximation of the correlated colour temperature of a linear sRGB colour in
+/// kelvin, or `None` if it has none.
+fn correlated_color_temperature(color: Vec3) -> Option<f32> {
+    let Vec3 { x: r, y: g, z: b } = color.max(Vec3::ZERO);
+    let xyz = Vec3::new(
+        0.4124 * r + 0.3576 * g + 0.1805 * b,
+        0.2126 * r + 0.7152 * g + 0.0722 * b,
+        0.0193 * r + 0.1192 * g + 0.9505 * b,
+    );
+    let sum = xyz.x + xyz.y + xyz.z;
+    if sum <= 0.0 {
+        return None;
+    }
+    let n = (xyz.x / sum - 0.3320) / (0.1858 - xyz.y / sum);
+    let kelvin = 449.0 * n.powi(3) + 3525.0 * n.powi(2) + 6823.3 * n + 5520.33;
+    (kelvin.is_finite() && kelvin > 0.0).then_some(kelvin)
+}
+
+/// The linear sRGB colour of a white LED at full, normalized so that its largest component is 1.
+fn white_led_rgb(chromaticity: Vec2) -> Vec3 {
+    let Vec2 { x, y } = chromaticity;
+    let xyz = Vec3::new(x / y, 1.0, (1.0 - x - y) / y);
+    let rgb = Vec3::new(
+        3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+        -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+        0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+    )
+    .max(Vec3::ZERO);
+    rgb / rgb.max_element()
+}
+
+/// The curve applied to every channel before quantization, encoding the sampled linear light
+/// into the values the LED driver expects.
+#[derive(Component, Clone, Debug, Default, PartialEq)]
+pub enum TransferCurve {
+    /// Send linear light unchanged.
+    #[default]
+    Linear,
+    /// Raise to the power of `1 / gamma`, as a driver decoding with this gamma expects. Gammas
+    /// that aren't positive and finite send linear light.
+    Gamma(f32),
+    /// The sRGB transfer function.
+    Srgb,
+    /// CIE 1976 L*, which is perceptually uniform.
+    CieLightness,
+    /// A lookup table sampled evenly over [0, 1] and interpolated linearly.
+    Lut(Vec<f32>),
+}
+
+impl TransferCurve {
+    pub fn apply(&self, value: f32) -> f32 {
+        let value = value.max(0.0);
+        match self {
+            TransferCurve::Linear => value,
+            TransferCurve::Gamma(gamma) if gamma.is_finite() && *gamma > 0.0 => {
+                value.powf(1.0 / gamma)
+            }
+            TransferCurve::Gamma(_) => value,
+            TransferCurve::Srgb => {
+                if value <= 0.0031308 {
+                    value * 12.92
+                } else {
+                    1.055 * value.powf(1.0 / 2.4) - 0.055
+                }
+            }
+            TransferCurve::CieLightness => {
+                const DELTA: f32 = 6.0 / 29.0;
+                let f = if value > DELTA.powi(3) {
+                    value.cbrt()
+                } else {
+                    value / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+                };
+                (116.0 * f - 16.0) / 100.0
+            }
+            TransferCurve::Lut(lut) => match lut.len() {
+                0 => value,
+                1 => lut[0],
+                len => {
+                    let position = value.min(1.0) * (len - 1) as f32;
+                    let index = (position as usize).min(len - 2);
+                    let t = position - index as f32;
+                    lut[index] + (lut[index + 1] - lut[index]) * t
+                }
+            },
+        }
+    }
+}
+
+/// The operator used to map sampled HDR colour into the range an LED can display.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LedTonemapper {
+    /// Clamp each channel, which blows out bright saturated colours into white.
+    #[default]
+    Clamp,
+    /// Reinhard applied to luminance, which preserves hue.
+    Reinhard,
+    /// Narkowicz's fit of the ACES filmic curve.
+    Aces,
+    /// Troy Sobotka's AgX, which desaturates highlights gracefully.
+    AgX,
+}
+
+/// Tone mapping for the LED output, independent of the camera's on-screen tonemapping.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct LedTonemapping {
+    pub tonemapper: LedTonemapper,
+    /// Exposure in stops, applied before tone mapping.
+    pub exposure: f32,
+}
+
+impl LedTonemapping {
+    pub fn new(tonemapper: LedTonemapper) -> Self {
+        Self {
+            tonemapper,
+            exposure: 0.0,
+        }
+    }
+
+    pub fn exposure(mut self, exposure: f32) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    pub(crate) fn apply(&self, pixel: &mut Pixel) {
+        let color = (pixel.color() * self.exposure.exp2()).max(Vec3::ZERO);
+        let color = match self.tonemapper {
+            LedTonemapper::Clamp => color,
+            LedTonemapper::Reinhard => {
+                let luminance = color.dot(Vec3::new(0.2126, 0.7152, 0.0722));
+                color / (1.0 + luminance)
+            }
+            LedTonemapper::Aces => {
+                (color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14)
+            }
+            LedTonemapper::AgX => agx(color),
+        };
+        pixel.set_color(color.clamp(Vec3::ZERO, Vec3::ONE));
+    }
+}
+
+fn agx(color: Vec3) -> Vec3 {
+    const MIN_EV: f32 = -12.47393;
+    const MAX_EV: f32 = 4.026069;
+    let inset = Mat3::from_cols(
+        Vec3::new(0.842479, 0.0423282, 0.0423757),
+        Vec3::new(0.0784336, 0.878469, 0.0784336),
+        Vec3::new(0.0792237, 0.0791661, 0.879143),
+    );
+    let outset = Mat3::from_cols(
+        Vec3::new(1.196879, -0.0528969, -0.0529716),
+        Vec3::new(-0.0980209, 1.151903, -0.0980435),
+        Vec3::new(-0.0990297, -0.0989612, 1.151074),
+    );
+
+    // Log encode into the AgX working space, then apply the sigmoid contrast curve
+    let color = inset * color;
+    let color = color.max(Vec3::splat(1e-10)).to_array().map(f32::log2);
+    let color =
+        ((Vec3::from_array(color) - MIN_EV) / (MAX_EV - MIN_EV)).clamp(Vec3::ZERO, Vec3::ONE);
+    let x2 = color * color;
+    let x4 = x2 * x2;
+    let color = 15.5 * x4 * x2 - 40.14 * x4 * color + 31.96 * x4 - 6.868 * x2 * color
+        + 0.4298 * x2
+        + 0.1191 * color
+        - 0.00232;
+
+    // Back to linear
+    let color = outset * color;
+    Vec3::from_array(color.max(Vec3::ZERO).to_array().map(|c| c.powf(2.2)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn led_chromaticity(kelvin: f32) -> Vec2 {
+        match WhiteExtraction::kelvin(kelvin) {
+            WhiteExtraction::ColorTemperature { chromaticity } => chromaticity,
+            extraction => panic!("expected colour temperature extraction, got {extraction:?}"),
+        }
+    }
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{actual} != {expected}"
+        );
+    }
+
+    #[test]
+    fn min_subtract_moves_the_common_part_into_white() {
+        let mut pixel = Pixel::rgb(0.8, 0.5, 0.3);
+        WhiteExtraction::MinSubtract.apply(&mut pixel);
+        assert_close(pixel.red, 0.5, 1e-6);
+        assert_close(pixel.green, 0.2, 1e-6);
+        assert_close(pixel.blue, 0.0, 1e-6);
+        assert_close(pixel.white, 0.3, 1e-6);
+    }
+
+    #[test]
+    fn white_boost_keeps_the_colour() {
+        let mut pixel = Pixel::rgb(0.8, 0.5, 0.3);
+        WhiteExtraction::WhiteBoost.apply(&mut pixel);
+        assert_eq!(pixel.color(), Vec3::new(0.8, 0.5, 0.3));
+        assert_close(pixel.white, 0.3, 1e-6);
+    }
+
+    #[test]
+    fn kelvin_follows_the_planckian_locus() {
+        // Published chromaticities of a black body at these temperatures
+        for (kelvin, x, y) in [
+            (2700.0, 0.4599, 0.4106),
+            (4000.0, 0.3805, 0.3768),
+            (6500.0, 0.3135, 0.3236),
+        ] {
+            let chromaticity = led_chromaticity(kelvin);
+            assert_close(chromaticity.x, x, 2e-3);
+            assert_close(chromaticity.y, y, 2e-3);
+        }
+    }
+
+    #[test]
+    fn white_at_the_leds_temperature_moves_entirely_into_white() {
+        for kelvin in [2700.0, 4000.0, 6500.0] {
+            let extraction = WhiteExtraction::kelvin(kelvin);
+            let chromaticity = led_chromaticity(kelvin);
+            let mut pixel = Pixel::default();
+            pixel.set_color(white_led_rgb(chromaticity) * 0.6);
+            extraction.apply(&mut pixel);
+            assert_close(pixel.white, 0.6, 1e-5);
+            assert_close(pixel.color().max_element(), 0.0, 1e-5);
+        }
+    }
+
+    #[test]
+    fn colour_temperature_extraction_never_goes_negative() {
+        let extraction = WhiteExtraction::kelvin(3000.0);
+        for color in [
+            Vec3::ONE,
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.2, 0.4, 1.0),
+            Vec3::new(0.9, 0.7, 0.1),
+        ] {
+            let mut pixel = Pixel::default();
+            pixel.set_color(color);
+            extraction.apply(&mut pixel);
+            assert!(pixel.color().min_element() >= -1e-6, "{color}");
+            assert!(pixel.white >= 0.0);
+        }
+
+        // Saturated red has no white in it at all
+        let mut pixel = Pixel::rgb(1.0, 0.0, 0.0);
+        extraction.apply(&mut pixel);
+        assert_eq!(pixel.white, 0.0);
+    }
+
+    #[test]
+    fn mccamy_estimates_the_temperature_of_white() {
+        // sRGB white is D65
+        let kelvin = correlated_color_temperature(Vec3::ONE).unwrap();
+        assert_close(kelvin, 6504.0, 50.0);
+        for temperature in [2700.0, 4000.0] {
+            let led = white_led_rgb(led_chromaticity(temperature));
+            let kelvin = correlated_color_temperature(led).unwrap();
+            assert_close(kelvin, temperature, 100.0);
+        }
+        assert_eq!(correlated_color_temperature(Vec3::ZERO), None);
+    }
+
+    #[test]
+    fn white_balance_splits_white_by_temperature() {
+        let balance = WhiteBalance::default();
+        let split = |color: Vec3| {
+            let mut pixel = Pixel {
+                white: 0.8,
+                ..default()
+            };
+            balance.split(color, &mut pixel);
+            assert_close(pixel.white + pixel.warm_white, 0.8, 1e-6);
+            pixel
+        };
+
+        // Cool white goes to the cool LED
+        let pixel = split(Vec3::ONE);
+        assert_close(pixel.warm_white, 0.0, 0.01);
+
+        // The warm LED's own colour goes to the warm LED
+        let pixel = split(white_led_rgb(led_chromaticity(2700.0)));
+        assert_close(pixel.white, 0.0, 0.05);
+
+        // Anything between is shared
+        let pixel = split(white_led_rgb(led_chromaticity(4000.0)));
+        assert!(pixel.white > 0.1 && pixel.warm_white > 0.1, "{pixel:?}");
+
+        // Black has no temperature, so the white is left alone
+        let pixel = split(Vec3::ZERO);
+        assert_eq!(pixel.white, 0.8);
+    }
+
+    #[test]
+    fn white_balance_without_a_warmer_led_stays_cool() {
+        let mut pixel = Pixel {
+            white: 0.5,
+            ..default()
+        };
+        WhiteBalance::new(2700.0, 6500.0).split(Vec3::new(1.0, 0.6, 0.3), &mut pixel);
+        assert_eq!(pixel.white, 0.5);
+        assert_eq!(pixel.warm_white, 0.0);
+    }
+
+    #[test]
+    fn gamma_encodes_for_the_driver() {
+        let curve = TransferCurve::Gamma(2.2);
+        assert_eq!(curve.apply(0.0), 0.0);
+        assert_eq!(curve.apply(1.0), 1.0);
+        assert_close(curve.apply(0.5), 0.5f32.powf(1.0 / 2.2), 1e-6);
+        // Decoding with the same gamma gives back linear light
+        assert_close(curve.apply(0.2).powf(2.2), 0.2, 1e-6);
+        assert_eq!(curve.apply(-0.5), 0.0);
+    }
+
+    #[test]
+    fn invalid_gammas_send_linear_light() {
+        for gamma in [0.0, -2.2, f32::NAN, f32::INFINITY] {
+            let curve = TransferCurve::Gamma(gamma);
+            for value in [0.0, 0.25, 0.5, 1.0] {
+                assert_eq!(curve.apply(value), value, "gamma {gamma}");
+            }
+        }
+    }
+
+    #[test]
+    fn srgb_and_cie_lightness_match_their_references() {
+        assert_close(TransferCurve::Srgb.apply(0.0), 0.0, 1e-6);
+        assert_close(TransferCurve::Srgb.apply(0.002), 0.02584, 1e-5);
+        assert_close(TransferCurve::Srgb.apply(0.5), 0.7354, 1e-4);
+        assert_close(TransferCurve::Srgb.apply(1.0), 1.0, 1e-6);
+
+        // Middle grey is close to half lightness
+        assert_close(TransferCurve::CieLightness.apply(0.0), 0.0, 1e-6);
+        assert_close(TransferCurve::CieLightness.apply(0.005), 0.04516, 1e-5);
+        assert_close(TransferCurve::CieLightness.apply(0.18), 0.4950, 1e-4);
+        assert_close(TransferCurve::CieLightness.apply(1.0), 1.0, 1e-6);
+    }
+
+    #[test]
+    fn lut_interpolates_between_entries() {
+        let curve = TransferCurve::Lut(vec![0.0, 0.25, 1.0]);
+        assert_eq!(curve.apply(0.0), 0.0);
+        assert_eq!(curve.apply(0.5), 0.25);
+        assert_eq!(curve.apply(1.0), 1.0);
+        assert_close(curve.apply(0.25), 0.125, 1e-6);
+        assert_close(curve.apply(0.75), 0.625, 1e-6);
+        // Values outside [0, 1] hold the end entries
+        assert_eq!(curve.apply(-1.0), 0.0);
+        assert_eq!(curve.apply(2.0), 1.0);
+    }
+
+    #[test]
+    fn short_luts_degrade_gracefully() {
+        assert_eq!(TransferCurve::Lut(vec![]).apply(0.3), 0.3);
+        assert_eq!(TransferCurve::Lut(vec![0.7]).apply(0.3), 0.7);
+    }
+
+    #[test]
+    fn tonemappers_are_monotonic_and_stay_in_range() {
+        // Greys and a few hues, from black up to well beyond white
+        let hues = [
+            Vec3::ONE,
+            Vec3::new(1.0, 0.5, 0.1),
+            Vec3::new(0.2, 0.6, 1.0),
+            Vec3::new(1.0, 0.0, 0.0),
+        ];
+        for tonemapper in [
+            LedTonemapper::Clamp,
+            LedTonemapper::Reinhard,
+            LedTonemapper::Aces,
+            LedTonemapper::AgX,
+        ] {
+            let tonemapping = LedTonemapping::new(tonemapper);
+            for hue in hues {
+                let mut previous = Vec3::ZERO;
+                for step in 0..=400 {
+                    let mut pixel = Pixel::default();
+                    pixel.set_color(hue * step as f32 * 0.05);
+                    tonemapping.apply(&mut pixel);
+                    let color = pixel.color();
+                    assert!(
+                        color.min_element() >= 0.0 && color.max_element() <= 1.0,
+                        "{tonemapper:?} of {hue} at step {step} is out of range: {color}"
+                    );
+                    // Brighter input never darkens any channel
+                    assert!(
+                        (color - previous).min_element() >= -1e-6,
+                        "{tonemapper:?} of {hue} darkens at step {step}: {previous} > {color}"
+                    );
+                    previous = color;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn exposure_scales_before_tonemapping() {
+        let mut pixel = Pixel::rgb(0.25, 0.25, 0.25);
+        LedTonemapping::new(LedTonemapper::Clamp)
+            .exposure(1.0)
+            .apply(&mut pixel);
+        assert_eq!(pixel.color(), Vec3::splat(0.5));
+    }
+}
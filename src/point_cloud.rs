@@ -0,0 +1,278 @@
+use crate::text_asset::{TextAsset, TextAssetLoader};
+use crate::{LedArea, LedPoints};
+use bevy::prelude::*;
+use bevy::window::{PrimaryWindow, WindowResized, WindowScaleFactorChanged};
+use std::fmt;
+use std::str::FromStr;
+
+pub struct PointCloudPlugin;
+
+impl Plugin for PointCloudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<PointCloud>()
+            .init_asset_loader::<PointCloudLoader>()
+            .add_systems(PostUpdate, update_point_cloud_points);
+    }
+}
+
+/// The positions of a set of LEDs, loaded from a `.points.csv` file of `x, y` rows with an
+/// optional third index column. Rows are in index order when there is one, otherwise in file
+/// order.
+#[derive(Asset, TypePath, Clone, Debug, Default, PartialEq)]
+pub struct PointCloud {
+    /// The positions in the file's units, with y increasing downwards like the screen.
+    pub points: Vec<Vec2>,
+}
+
+impl PointCloud {
+    /// Scale the points uniformly to fit within `size`, centred.
+    pub fn fit(&self, size: Vec2) -> Vec<Vec2> {
+        let Some(first) = self.points.first() else {
+            return Vec::new();
+        };
+        let (min, max) = self
+            .points
+            .iter()
+            .fold((*first, *first), |(min, max), p| (min.min(*p), max.max(*p)));
+
+        // A line of points only needs to fit along its length
+        let extent = max - min;
+        let scale = match (extent.x > 0.0, extent.y > 0.0) {
+            (true, true) => (size / extent).min_element(),
+            (true, false) => size.x / extent.x,
+            (false, true) => size.y / extent.y,
+            (false, false) => 0.0,
+        };
+        let offset = (size - extent * scale) / 2.0;
+        self.points
+            .iter()
+            .map(|p| offset + (*p - min) * scale)
+            .collect()
+    }
+}
+
+impl FromStr for PointCloud {
+    type Err = PointCloudError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rows = Vec::new();
+        let mut header = false;
+        for (i, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+            let row = match fields.as_slice() {
+                [x, y] | [x, y, ""] => parse_row(x, y, None),
+                [x, y, index] => parse_row(x, y, Some(*index)),
+                _ => None,
+            };
+            match row {
+                Some(row) => rows.push(row),
+                // The first line may be a header naming the columns
+                None if rows.is_empty() && !header => header = true,
+                None => return Err(PointCloudError::InvalidLine(i + 1)),
+            }
+        }
+
+        let indexed = rows.iter().filter(|(_, index)| index.is_some()).count();
+        if indexed > 0 && indexed < rows.len() {
+            return Err(PointCloudError::MissingIndex);
+        }
+        rows.sort_by_key(|(_, index)| *index);
+        for pair in rows.windows(2) {
+            if let (Some(a), Some(b)) = (pair[0].1, pair[1].1) {
+                if a == b {
+                    return Err(PointCloudError::DuplicateIndex(a));
+                }
+            }
+        }
+
+        Ok(Self {
+            points: rows.into_iter().map(|(point, _)| point).collect(),
+        })
+    }
+}
+
+fn parse_row(x: &str, y: &str, index: Option<&str>) -> Option<(Vec2, Option<u32>)> {
+    let point = Vec2::new(x.parse().ok()?, y.parse().ok()?);
+    let index = match index {
+        Some(index) => Some(index.parse().ok()?),
+        None => None,
+    };
+    Some((point, index))
+}
+
+#[derive(Debug)]
+pub enum PointCloudError {
+    /// The 1-based line number of a row that couldn't be parsed.
+    InvalidLine(usize),
+    /// Some rows have an index and others don't.
+    MissingIndex,
+    /// More than one row has this index.
+    DuplicateIndex(u32),
+}
+
+impl fmt::Display for PointCloudError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointCloudError::InvalidLine(line) => {
+                write!(f, "invalid point cloud data on line {line}")
+            }
+            PointCloudError::MissingIndex => {
+                write!(f, "either every point or no point should have an index")
+            }
+            PointCloudError::DuplicateIndex(index) => {
+                write!(f, "more than one point has index {index}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PointCloudError {}
+
+impl TextAsset for PointCloud {
+    const EXTENSIONS: &'static [&'static str] = &["points.csv"];
+}
+
+/// Loads `.points.csv` files as `PointCloud` assets, leaving other CSV files to the app.
+pub type PointCloudLoader = TextAssetLoader<PointCloud>;
+
+/// Lays a `LedArea`'s LEDs out at the points of a `PointCloud`, fitted to the primary window.
+/// The area has one LED per point.
+#[derive(Component, Clone, Debug)]
+pub struct LedPointCloud {
+    pub cloud: Handle<PointCloud>,
+    /// The radius sampled around each point, in screen pixels.
+    pub radius: f32,
+}
+
+impl LedPointCloud {
+    pub fn new(cloud: Handle<PointCloud>) -> Self {
+        Self { cloud, radius: 5.0 }
+    }
+
+    pub fn radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+}
+
+fn update_point_cloud_points(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<PointCloud>>,
+    mut window_resized: EventReader<WindowResized>,
+    mut window_scale_factor_changed: EventReader<WindowScaleFactorChanged>,
+    point_clouds: Res<Assets<PointCloud>>,
+    primary_window_q: Query<&Window, With<PrimaryWindow>>,
+    mut clouds_q: Query<(Entity, Ref<LedPointCloud>, &mut LedArea)>,
+) {
+    // Any of these may move every cloud's points
+    let asset_changed = asset_events.read().count() > 0;
+    let window_resized = window_resized.read().count() > 0;
+    let window_changed = window_scale_factor_changed.read().count() > 0 || window_resized;
+    let Ok(window) = primary_window_q.get_single() else {
+        return;
+    };
+    let window_size = Vec2::new(
+        window.physical_width() as f32,
+        window.physical_height() as f32,
+    );
+
+    for (entity, cloud, mut area) in clouds_q.iter_mut() {
+        if !cloud.is_changed() && !asset_changed && !window_changed {
+            continue;
+        }
+        let Some(point_cloud) = point_clouds.get(&cloud.cloud) else {
+            continue;
+        };
+
+        let points = point_cloud.fit(window_size);
+        if area.count != points.len() as u32 {
+            area.count = points.len() as u32;
+        }
+        commands.entity(entity).insert(LedPoints {
+            points,
+            radius: cloud.radius,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(csv: &str) -> Result<Vec<Vec2>, PointCloudError> {
+        csv.parse::<PointCloud>().map(|cloud| cloud.points)
+    }
+
+    #[test]
+    fn reads_rows_in_file_order() {
+        let points = parse("# Measured by hand\n0, 0\n\n10.5, 2\n-3, 4,\n").unwrap();
+        assert_eq!(
+            points,
+            [Vec2::ZERO, Vec2::new(10.5, 2.0), Vec2::new(-3.0, 4.0)]
+        );
+    }
+
+    #[test]
+    fn skips_a_header() {
+        let points = parse("x, y, index\n1, 2, 0\n3, 4, 1\n").unwrap();
+        assert_eq!(points, [Vec2::new(1.0, 2.0), Vec2::new(3.0, 4.0)]);
+        // Only the first line may be a header
+        assert!(matches!(
+            parse("x, y\n1, 2\nx, y\n"),
+            Err(PointCloudError::InvalidLine(3))
+        ));
+    }
+
+    #[test]
+    fn orders_rows_by_index() {
+        let points = parse("1, 1, 2\n2, 2, 0\n3, 3, 1\n").unwrap();
+        assert_eq!(
+            points,
+            [Vec2::splat(2.0), Vec2::splat(3.0), Vec2::splat(1.0)]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_rows() {
+        assert!(matches!(
+            parse("1, 2\n3, 4\n5\n"),
+            Err(PointCloudError::InvalidLine(3))
+        ));
+        assert!(matches!(
+            parse("1, 2\n\n3, four\n"),
+            Err(PointCloudError::InvalidLine(3))
+        ));
+        assert!(matches!(
+            parse("1, 2, 0\n3, 4, -1\n"),
+            Err(PointCloudError::InvalidLine(2))
+        ));
+    }
+
+    #[test]
+    fn rejects_mixed_and_duplicate_indices() {
+        assert!(matches!(
+            parse("1, 2, 0\n3, 4\n"),
+            Err(PointCloudError::MissingIndex)
+        ));
+        assert!(matches!(
+            parse("1, 2, 0\n3, 4, 1\n5, 6, 1\n"),
+            Err(PointCloudError::DuplicateIndex(1))
+        ));
+    }
+
+    #[test]
+    fn fits_within_the_size() {
+        let cloud = PointCloud {
+            points: vec![Vec2::ZERO, Vec2::new(10.0, 5.0)],
+        };
+        assert_eq!(
+            cloud.fit(Vec2::new(100.0, 100.0)),
+            [Vec2::new(0.0, 25.0), Vec2::new(100.0, 75.0)]
+        );
+    }
+}
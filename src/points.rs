@@ -0,0 +1,12 @@
+use bevy::prelude::*;
+use bevy::render::extract_component::ExtractComponent;
+
+/// Samples a `LedArea` around arbitrary points rather than by dividing its rectangle, for
+/// layouts that aren't straight strips. Points are in the same screen space as
+/// `LedArea::position`, in output order.
+#[derive(Component, ExtractComponent, Clone, Debug, Default, PartialEq)]
+pub struct LedPoints {
+    pub points: Vec<Vec2>,
+    /// The radius sampled around each point, which is also drawn in the preview.
+    pub radius: f32,
+}
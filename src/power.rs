@@ -0,0 +1,110 @@
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, RegisterDiagnostic};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// The estimated current drawn by every `LedArea` with a `PowerModel`, in milliamps, after
+/// limiting.
+pub const POWER_DRAW: DiagnosticPath = DiagnosticPath::const_new("pixelmap/power_draw");
+
+pub struct PowerPlugin;
+
+impl Plugin for PowerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PowerBudget>()
+            .register_diagnostic(Diagnostic::new(POWER_DRAW).with_suffix("mA"));
+    }
+}
+
+/// The current drawn by a `LedArea`'s LEDs, used to estimate its draw each frame and dim it
+/// when that exceeds the supply.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct PowerModel {
+    /// The current drawn by a single channel at full, in milliamps.
+    pub milliamps_per_channel: f32,
+    /// The limit of the area's own supply, in milliamps.
+    pub limit: Option<f32>,
+    /// The group of areas sharing a supply, limited by `PowerBudget`.
+    pub group: Option<String>,
+}
+
+impl PowerModel {
+    pub fn new(milliamps_per_channel: f32) -> Self {
+        Self {
+            milliamps_per_channel,
+            limit: None,
+            group: None,
+        }
+    }
+
+    /// A WS2812 style strip, which draws around 20mA per channel.
+    pub fn ws2812() -> Self {
+        Self::new(20.0)
+    }
+
+    pub fn limit(mut self, milliamps: f32) -> Self {
+        self.limit = Some(milliamps);
+        self
+    }
+
+    pub fn group(mut self, group: impl Into<String>) -> Self {
+        self.group = Some(group.into());
+        self
+    }
+
+    /// The estimated draw of channel values that have already been through the transfer curve,
+    /// as the driver's duty cycle is what determines the current.
+    pub(crate) fn estimate(&self, values: &[f32]) -> f32 {
+        values
+            .iter()
+            .map(|value| value.clamp(0.0, 1.0))
+            .sum::<f32>()
+            * self.milliamps_per_channel
+    }
+}
+
+/// Limits on the current drawn by supplies shared between areas. When the estimated draw
+/// exceeds a limit, every area drawing from it is dimmed by the same factor.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct PowerBudget {
+    /// The limit across every area with a `PowerModel`, in milliamps.
+    pub global: Option<f32>,
+    /// The limit of each `PowerModel` group, in milliamps.
+    pub groups: HashMap<String, f32>,
+}
+
+impl PowerBudget {
+    pub fn global(mut self, milliamps: f32) -> Self {
+        self.global = Some(milliamps);
+        self
+    }
+
+    pub fn group(mut self, group: impl Into<String>, milliamps: f32) -> Self {
+        self.groups.insert(group.into(), milliamps);
+        self
+    }
+}
+
+/// The estimated draw of a `LedArea` for the last frame it was sent.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct PowerDraw {
+    /// The draw the sampled pixels would have required, in milliamps.
+    pub requested: f32,
+    /// The brightness scale applied to stay within budget.
+    pub scale: f32,
+}
+
+impl PowerDraw {
+    /// The draw after limiting, in milliamps.
+    pub fn limited(&self) -> f32 {
+        self.requested * self.scale
+    }
+}
+
+/// The scale that brings `draw` within `limit`.
+pub(crate) fn budget_scale(draw: f32, limit: f32) -> f32 {
+    if draw > limit && draw > 0.0 {
+        limit.max(0.0) / draw
+    } else {
+        1.0
+    }
+}
@@ -0,0 +1,135 @@
+use crate::path::spaced_along;
+use crate::{LedArea, LedPoints};
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+/// The number of line segments each turn of a spiral is flattened into.
+const SPIRAL_STEPS_PER_TURN: f32 = 64.0;
+
+pub struct RingPlugin;
+
+impl Plugin for RingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, update_ring_points);
+    }
+}
+
+/// The shape of a `LedRing`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RingLayout {
+    /// A full circle, with the first LED at the start angle.
+    Ring,
+    /// Part of a circle, spanning `sweep` radians from the start angle. Negative sweeps run
+    /// counter-clockwise.
+    Arc { sweep: f32 },
+    /// An Archimedean spiral winding outwards over `turns` turns, from `inner_radius` as a
+    /// fraction of the outer radius.
+    Spiral { turns: f32, inner_radius: f32 },
+}
+
+/// Lays a `LedArea`'s LEDs out on a circle inscribed in its rectangle, so that it can be moved,
+/// resized and rotated like any other area.
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct LedRing {
+    pub layout: RingLayout,
+    /// The angle of the first LED in radians, clockwise from the right of the area.
+    pub start_angle: f32,
+    /// Whether the LEDs run clockwise from the first, otherwise counter-clockwise.
+    pub clockwise: bool,
+    /// The radius sampled around each LED, in screen pixels.
+    pub footprint: f32,
+}
+
+impl LedRing {
+    pub fn new(layout: RingLayout) -> Self {
+        Self {
+            layout,
+            start_angle: 0.0,
+            clockwise: true,
+            footprint: 5.0,
+        }
+    }
+
+    pub fn ring() -> Self {
+        Self::new(RingLayout::Ring)
+    }
+
+    pub fn arc(sweep: f32) -> Self {
+        Self::new(RingLayout::Arc { sweep })
+    }
+
+    pub fn spiral(turns: f32, inner_radius: f32) -> Self {
+        Self::new(RingLayout::Spiral {
+            turns,
+            inner_radius,
+        })
+    }
+
+    pub fn start_angle(mut self, start_angle: f32) -> Self {
+        self.start_angle = start_angle;
+        self
+    }
+
+    pub fn clockwise(mut self, clockwise: bool) -> Self {
+        self.clockwise = clockwise;
+        self
+    }
+
+    pub fn footprint(mut self, footprint: f32) -> Self {
+        self.footprint = footprint;
+        self
+    }
+
+    /// The screen position of each of the area's LEDs.
+    pub fn sample(&self, area: &LedArea) -> Vec<Vec2> {
+        let count = area.count;
+        let radius = area.size.min_element() / 2.0;
+        let direction = if self.clockwise { 1.0 } else { -1.0 };
+        let on_circle = |angle: f32, radius: f32| {
+            let angle = self.start_angle + angle * direction;
+            area.size / 2.0 + Vec2::new(angle.cos(), angle.sin()) * radius
+        };
+
+        let local: Vec<Vec2> = match self.layout {
+            RingLayout::Ring => (0..count)
+                .map(|i| on_circle(i as f32 / count as f32 * TAU, radius))
+                .collect(),
+            RingLayout::Arc { sweep } => (0..count)
+                .map(|i| on_circle((i as f32 + 0.5) / count as f32 * sweep, radius))
+                .collect(),
+            RingLayout::Spiral {
+                turns,
+                inner_radius,
+            } => {
+                let inner_radius = inner_radius.clamp(0.0, 1.0) * radius;
+                let steps = (turns.abs() * SPIRAL_STEPS_PER_TURN).ceil().max(1.0) as u32;
+                let spiral = (0..=steps)
+                    .map(|i| {
+                        let t = i as f32 / steps as f32;
+                        on_circle(t * turns * TAU, inner_radius + (radius - inner_radius) * t)
+                    })
+                    .collect::<Vec<_>>();
+                spaced_along(&spiral, count)
+            }
+        };
+
+        // Rotate around the area's top left corner, as the compute shader does
+        let (sin, cos) = area.rotation.sin_cos();
+        local
+            .into_iter()
+            .map(|p| area.position + Vec2::new(cos * p.x + sin * p.y, cos * p.y - sin * p.x))
+            .collect()
+    }
+}
+
+fn update_ring_points(
+    mut commands: Commands,
+    rings_q: Query<(Entity, &LedArea, &LedRing), Or<(Changed<LedArea>, Changed<LedRing>)>>,
+) {
+    for (entity, area, ring) in rings_q.iter() {
+        commands.entity(entity).insert(LedPoints {
+            points: ring.sample(area),
+            radius: ring.footprint,
+        });
+    }
+}
@@ -1,17 +1,314 @@
+use crate::output::{encode_output_data, DmxPatch, OutputData, PixelStraddle, DMX_START_CODE};
+use bevy::ecs::entity::EntityHashMap;
 use bevy::prelude::*;
+use bevy::utils::hashbrown::hash_map::Entry;
+use bevy::utils::{HashMap, HashSet};
 use sacn::packet::ACN_SDT_MULTICAST_PORT;
-use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
-#[derive(Resource, Deref, DerefMut)]
-pub struct SacnSource(sacn::source::SacnSource);
+/// The source name used when a `SacnOutput` doesn't specify one.
+pub const DEFAULT_SACN_SOURCE_NAME: &str = "nannou";
+
+/// The highest universe that may carry DMX data.
+const MAX_SACN_UNIVERSE: u16 = 63999;
+
+pub struct SacnPlugin;
+
+impl Plugin for SacnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SacnSource>()
+            .init_resource::<SacnUniverses>()
+            .observe(patch_sacn_output)
+            .observe(terminate_sacn_output)
+            .add_systems(First, send_sacn_universes.after(encode_output_data))
+            .add_systems(Last, terminate_sacn_streams.run_if(on_event::<AppExit>()));
+    }
+}
+
+/// The sACN sources shared by all outputs, keyed by source name. Insert this resource before
+/// adding the plugin to send from a specific interface.
+///
+/// This no longer derefs to a single `sacn::source::SacnSource`, as outputs may send from
+/// several named sources. Use [`SacnSource::source`] to reach one, e.g. with
+/// [`DEFAULT_SACN_SOURCE_NAME`] for the source outputs use by default.
+#[derive(Resource)]
+pub struct SacnSource {
+    interface_ip: IpAddr,
+    sources: HashMap<String, sacn::source::SacnSource>,
+}
 
 impl SacnSource {
     pub fn new(interface_ip: IpAddr) -> Self {
-        let src = sacn::source::SacnSource::with_ip(
-            "nannou",
-            SocketAddr::new(interface_ip, ACN_SDT_MULTICAST_PORT),
-        )
-        .unwrap();
-        Self(src)
+        Self {
+            interface_ip,
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Get the source with the given name, creating it on first use.
+    pub fn source(
+        &mut self,
+        name: &str,
+    ) -> sacn::error::errors::Result<&mut sacn::source::SacnSource> {
+        match self.sources.entry(name.to_string()) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                let source =
+                    sacn::source::SacnSource::with_ip(name, SocketAddr::new(self.interface_ip, 0))?;
+                Ok(entry.insert(source))
+            }
+        }
+    }
+}
+
+impl Default for SacnSource {
+    fn default() -> Self {
+        Self::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+    }
+}
+
+/// Sends a `LedArea`'s pixel data as E1.31 (sACN).
+#[derive(Component, Clone, Debug)]
+pub struct SacnOutput {
+    /// The first universe, continuing into consecutive universes as required.
+    pub universe: u16,
+    /// The priority of the data, from 0 to 200.
+    pub priority: u8,
+    /// A unicast destination, or `None` to send to the universe's multicast address.
+    pub destination: Option<SocketAddr>,
+    /// The name of the source on the shared `SacnSource` to send from.
+    pub source_name: String,
+    /// Where the area's channels start, continuing into consecutive universes.
+    pub patch: DmxPatch,
+    /// The synchronization universe of the output's group. Receivers hold the data of every
+    /// universe in the group until a single sync packet is sent after the whole frame.
+    pub sync_universe: Option<u16>,
+}
+
+impl SacnOutput {
+    pub fn new(universe: u16) -> Self {
+        Self {
+            universe,
+            priority: 100,
+            destination: None,
+            source_name: DEFAULT_SACN_SOURCE_NAME.to_string(),
+            patch: DmxPatch::default(),
+            sync_universe: None,
+        }
+    }
+
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn unicast(mut self, ip: impl Into<IpAddr>) -> Self {
+        self.destination = Some(SocketAddr::new(ip.into(), ACN_SDT_MULTICAST_PORT));
+        self
+    }
+
+    pub fn multicast(mut self) -> Self {
+        self.destination = None;
+        self
+    }
+
+    pub fn source_name(mut self, source_name: impl Into<String>) -> Self {
+        self.source_name = source_name.into();
+        self
+    }
+
+    pub fn start_channel(mut self, start_channel: u16) -> Self {
+        self.patch.start_channel = start_channel;
+        self
+    }
+
+    pub fn straddle(mut self, straddle: PixelStraddle) -> Self {
+        self.patch.straddle = straddle;
+        self
+    }
+
+    pub fn sync_universe(mut self, sync_universe: u16) -> Self {
+        self.sync_universe = Some(sync_universe);
+        self
+    }
+}
+
+#[derive(Default)]
+struct SacnUniverse {
+    priority: u8,
+    destination: Option<SocketAddr>,
+    sync_universe: Option<u16>,
+    data: Vec<u8>,
+    dirty: bool,
+}
+
+/// A universe registered on a named source.
+type SacnUniverseKey = (String, u16);
+
+#[derive(Resource, Default)]
+struct SacnUniverses {
+    /// The universes registered on the shared sources.
+    universes: HashMap<SacnUniverseKey, SacnUniverse>,
+    /// The universes each output is patched into.
+    outputs: EntityHashMap<Vec<SacnUniverseKey>>,
+    /// The synchronization universes registered on the shared sources.
+    sync_universes: HashSet<SacnUniverseKey>,
+}
+
+fn patch_sacn_output(
+    trigger: Trigger<OutputData>,
+    outputs_q: Query<&SacnOutput>,
+    mut source: ResMut<SacnSource>,
+    mut universes: ResMut<SacnUniverses>,
+) {
+    let Ok(output) = outputs_q.get(trigger.entity()) else {
+        return;
+    };
+
+    let universes = &mut *universes;
+    let OutputData {
+        data,
+        bytes_per_pixel,
+        ..
+    } = trigger.event();
+    if let Some(sync_universe) = output.sync_universe {
+        let key = (output.source_name.clone(), sync_universe);
+        if !universes.sync_universes.contains(&key) {
+            let source = match source.source(&output.source_name) {
+                Ok(source) => source,
+                Err(err) => {
+                    warn_once!("Failed to create sACN source {}: {err}", output.source_name);
+                    return;
+                }
+            };
+            if let Err(err) = source.register_universe(sync_universe) {
+                warn!("Failed to register sACN sync universe {sync_universe}: {err}");
+            }
+            universes.sync_universes.insert(key);
+        }
+    }
+
+    let mut keys = Vec::new();
+    for chunk in output.patch.split(data.len(), *bytes_per_pixel) {
+        let universe_id = output.universe.saturating_add(chunk.universe);
+        if universe_id > MAX_SACN_UNIVERSE {
+            warn!(
+                "sACN output for {} exceeds the last universe",
+                trigger.entity()
+            );
+            break;
+        }
+
+        let key = (output.source_name.clone(), universe_id);
+        let universe = match universes.universes.entry(key.clone()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let source = match source.source(&output.source_name) {
+                    Ok(source) => source,
+                    Err(err) => {
+                        warn_once!("Failed to create sACN source {}: {err}", output.source_name);
+                        break;
+                    }
+                };
+                if let Err(err) = source.register_universe(universe_id) {
+                    warn!("Failed to register sACN universe {universe_id}: {err}");
+                }
+                entry.insert(SacnUniverse::default())
+            }
+        };
+
+        // Rebuilt from this frame's areas, so a shrinking area doesn't leave stale channels
+        if !universe.dirty {
+            universe.data.clear();
+        }
+        let end = chunk.offset + chunk.range.len();
+        if universe.data.len() < end {
+            universe.data.resize(end, 0);
+        }
+        universe.priority = output.priority;
+        universe.destination = output.destination;
+        universe.sync_universe = output.sync_universe;
+        universe.data[chunk.offset..end].copy_from_slice(&data[chunk.range]);
+        universe.dirty = true;
+        keys.push(key);
+    }
+
+    universes.outputs.insert(trigger.entity(), keys);
+}
+
+fn send_sacn_universes(mut source: ResMut<SacnSource>, mut universes: ResMut<SacnUniverses>) {
+    let mut sync_groups = HashSet::new();
+    for ((source_name, universe_id), universe) in universes.universes.iter_mut() {
+        if !universe.dirty {
+            continue;
+        }
+        universe.dirty = false;
+        if let Some(sync_universe) = universe.sync_universe {
+            sync_groups.insert((source_name.clone(), sync_universe, universe.destination));
+        }
+
+        let Ok(source) = source.source(source_name) else {
+            continue;
+        };
+        let mut data = Vec::with_capacity(universe.data.len() + 1);
+        data.push(DMX_START_CODE);
+        data.extend_from_slice(&universe.data);
+        if let Err(err) = source.send(
+            &[*universe_id],
+            &data,
+            Some(universe.priority),
+            universe.destination,
+            universe.sync_universe,
+        ) {
+            warn!("Failed to send sACN universe {universe_id}: {err}");
+        }
+    }
+
+    // Latch every universe of the frame at once now that all of their data has been sent
+    for (source_name, sync_universe, destination) in sync_groups {
+        let Ok(source) = source.source(&source_name) else {
+            continue;
+        };
+        if let Err(err) = source.send_sync_packet(sync_universe, destination) {
+            warn!("Failed to send sACN sync packet for universe {sync_universe}: {err}");
+        }
+    }
+}
+
+fn terminate_sacn_output(
+    trigger: Trigger<OnRemove, SacnOutput>,
+    mut source: ResMut<SacnSource>,
+    mut universes: ResMut<SacnUniverses>,
+) {
+    let universes = &mut *universes;
+    let Some(keys) = universes.outputs.remove(&trigger.entity()) else {
+        return;
+    };
+
+    for key in keys {
+        // Another output may still be patched into this universe
+        if universes.outputs.values().any(|other| other.contains(&key)) {
+            continue;
+        }
+
+        universes.universes.remove(&key);
+        let (source_name, universe_id) = key;
+        let Ok(source) = source.source(&source_name) else {
+            continue;
+        };
+        if let Err(err) = source.terminate_stream(universe_id, DMX_START_CODE) {
+            warn!("Failed to terminate sACN universe {universe_id}: {err}");
+        }
+    }
+}
+
+fn terminate_sacn_streams(mut source: ResMut<SacnSource>, universes: Res<SacnUniverses>) {
+    for (source_name, universe_id) in universes.universes.keys() {
+        let Ok(source) = source.source(source_name) else {
+            continue;
+        };
+        if let Err(err) = source.terminate_stream(*universe_id, DMX_START_CODE) {
+            warn!("Failed to terminate sACN universe {universe_id}: {err}");
+        }
     }
 }
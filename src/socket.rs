@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+
+/// Bind a non-blocking UDP socket that may broadcast to `addr`, sharing the port with any other
+/// software on the machine that does the same.
+pub(crate) fn bind_udp_socket(addr: impl ToSocketAddrs) -> io::Result<UdpSocket> {
+    let addr = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to bind to"))?;
+    let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_reuse_address(true)?;
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    socket.set_reuse_port(true)?;
+    socket.set_broadcast(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    Ok(socket.into())
+}
+
+/// A socket resource that a plugin binds once one of its outputs needs it.
+pub(crate) trait OutputSocket: Resource + Sized {
+    /// The protocol sent on the socket.
+    const PROTOCOL: &'static str;
+
+    fn bind_default() -> io::Result<Self>;
+}
+
+/// Bind `S` once there is an `O` to send to, so that apps without the protocol don't hold a
+/// socket. A failure is warned about once rather than retried every frame.
+pub(crate) fn bind_output_socket<S: OutputSocket, O: Component>(
+    mut commands: Commands,
+    outputs_q: Query<(), With<O>>,
+    mut failed: Local<bool>,
+) {
+    if *failed || outputs_q.is_empty() {
+        return;
+    }
+    match S::bind_default() {
+        Ok(socket) => commands.insert_resource(socket),
+        Err(err) => {
+            warn!(
+                "Failed to bind the {} socket, nothing will be sent: {err}",
+                S::PROTOCOL
+            );
+            *failed = true;
+        }
+    }
+}
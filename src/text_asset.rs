@@ -0,0 +1,77 @@
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+/// An asset parsed from a UTF-8 text file.
+pub trait TextAsset: Asset + FromStr {
+    /// The file extensions the asset is loaded from, without the leading dot.
+    const EXTENSIONS: &'static [&'static str];
+}
+
+/// Loads `TextAsset`s by reading the whole file and parsing it.
+pub struct TextAssetLoader<A>(PhantomData<fn() -> A>);
+
+impl<A> Default for TextAssetLoader<A> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<A> AssetLoader for TextAssetLoader<A>
+where
+    A: TextAsset,
+    A::Err: std::error::Error + Send + Sync + 'static,
+{
+    type Asset = A;
+    type Settings = ();
+    type Error = TextAssetError<A::Err>;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text).await?;
+        text.parse().map_err(TextAssetError::Parse)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        A::EXTENSIONS
+    }
+}
+
+/// A failure to load a `TextAsset`, either reading the file or parsing its contents.
+#[derive(Debug)]
+pub enum TextAssetError<E> {
+    Io(std::io::Error),
+    Parse(E),
+}
+
+impl<E: fmt::Display> fmt::Display for TextAssetError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextAssetError::Io(err) => write!(f, "failed to read asset: {err}"),
+            TextAssetError::Parse(err) => err.fmt(f),
+        }
+    }
+}
+
+impl<E: std::error::Error + 'static> std::error::Error for TextAssetError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TextAssetError::Io(err) => Some(err),
+            TextAssetError::Parse(err) => err.source(),
+        }
+    }
+}
+
+impl<E> From<std::io::Error> for TextAssetError<E> {
+    fn from(err: std::io::Error) -> Self {
+        TextAssetError::Io(err)
+    }
+}
@@ -1,4 +1,4 @@
-use crate::LedArea;
+use crate::{LedArea, LedPath, LedPointCloud, XModelString};
 use bevy::prelude::*;
 use bevy::render::camera::RenderTarget;
 use bevy::render::primitives::Aabb;
@@ -16,8 +16,10 @@ impl Plugin for UiPlugin {
             (
                 despawn_removed_areas,
                 propagate_movement,
+                propagate_control_points,
                 update_corner_positions,
                 spawn_led,
+                sync_control_points,
                 update_cursor_state,
                 update_cursor_icon,
             ),
@@ -41,11 +43,19 @@ enum ResizeHandle {
 #[derive(Component)]
 struct RotationHandle;
 
+/// A drag handle for one of a `LedPath`'s control points.
+#[derive(Component)]
+struct ControlPoint {
+    area: Entity,
+    index: usize,
+}
+
 #[derive(Component, Default)]
 struct CursorState {
     resize_handle: Option<ResizeHandle>,
     rotate: bool,
     main_rectangle: bool,
+    control_point: bool,
 }
 
 #[derive(Component, Copy, Clone)]
@@ -59,7 +69,15 @@ pub struct MeshRef(Entity);
 
 fn spawn_led(
     mut commands: Commands,
-    added_leds_q: Query<(Entity, &LedArea), Added<LedArea>>,
+    added_leds_q: Query<
+        (Entity, &LedArea),
+        (
+            Added<LedArea>,
+            Without<LedPath>,
+            Without<LedPointCloud>,
+            Without<XModelString>,
+        ),
+    >,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
 ) {
@@ -137,16 +155,94 @@ fn spawn_led(
     }
 }
 
+/// Keep each `LedPath`'s drag handles in step with its control points, respawning them when the
+/// number of points changes and otherwise moving them to where the points are.
+fn sync_control_points(
+    mut commands: Commands,
+    changed_paths_q: Query<(Entity, &LedPath), Changed<LedPath>>,
+    mut control_points_q: Query<(Entity, &ControlPoint, &mut Transform)>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<UiCamera>>,
+    windows_q: Query<&Window>,
+    primary_window_q: Query<&Window, With<PrimaryWindow>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let (ui_camera, ui_camera_transform) = camera_q.single();
+    let window = ui_window(ui_camera, &windows_q, &primary_window_q);
+
+    for (entity, path) in changed_paths_q.iter() {
+        // Control points are in screen pixels, the handles are in world space
+        let positions = path
+            .control_points
+            .iter()
+            .map(|point| {
+                ui_camera.viewport_to_world_2d(ui_camera_transform, *point / window.scale_factor())
+            })
+            .collect::<Vec<_>>();
+
+        let handles = control_points_q
+            .iter()
+            .filter(|(_, control_point, _)| control_point.area == entity)
+            .count();
+        if handles == positions.len() {
+            for (_, control_point, mut transform) in control_points_q.iter_mut() {
+                if control_point.area != entity {
+                    continue;
+                }
+                let Some(Some(position)) = positions.get(control_point.index) else {
+                    continue;
+                };
+                // Only move handles that are out of place, so a handle being dragged isn't fought
+                if transform.translation.truncate().distance_squared(*position) > 0.01 {
+                    transform.translation = position.extend(transform.translation.z);
+                }
+            }
+            continue;
+        }
+
+        for (handle, control_point, _) in control_points_q.iter() {
+            if control_point.area == entity {
+                commands.entity(handle).despawn_recursive();
+            }
+        }
+        for (index, position) in positions.into_iter().enumerate() {
+            let Some(position) = position else {
+                continue;
+            };
+            commands.spawn((
+                ControlPoint {
+                    area: entity,
+                    index,
+                },
+                MaterialMesh2dBundle {
+                    mesh: meshes.add(Circle::new(7.0)).into(),
+                    transform: Transform::from_translation(position.extend(0.0)),
+                    material: materials.add(ColorMaterial::from(Color::NONE)),
+                    ..default()
+                },
+                PickableBundle::default(),
+                On::<Pointer<DragStart>>::target_insert(Pickable::IGNORE),
+                On::<Pointer<DragEnd>>::target_insert(Pickable::default()),
+                On::<Pointer<Drag>>::run(drag_body),
+                On::<Pointer<Over>>::target_insert(Hover),
+                On::<Pointer<Out>>::target_remove::<Hover>(),
+            ));
+        }
+    }
+}
+
 fn update_cursor_state(
     mut cursor_state: Query<&mut CursorState, With<UiCamera>>,
     resize_handles: Query<(&ResizeHandle, &Hover)>,
     rotation_handles: Query<(), (With<RotationHandle>, With<Hover>)>,
     main_rectangles: Query<(), (With<InitialDimensions>, With<Hover>)>,
+    control_points: Query<(), (With<ControlPoint>, With<Hover>)>,
 ) {
     let mut cursor_state = cursor_state.single_mut();
     cursor_state.resize_handle = resize_handles.iter().next().map(|(handle, _)| *handle);
     cursor_state.rotate = !rotation_handles.is_empty();
     cursor_state.main_rectangle = !main_rectangles.is_empty();
+    cursor_state.control_point = !control_points.is_empty();
 }
 
 fn update_cursor_icon(
@@ -165,7 +261,7 @@ fn update_cursor_icon(
             ResizeHandle::BottomLeft => CursorIcon::SwResize,
             ResizeHandle::BottomRight => CursorIcon::SeResize,
         };
-    } else if cursor_state.main_rectangle {
+    } else if cursor_state.main_rectangle || cursor_state.control_point {
         window.cursor.icon = CursorIcon::Move;
     } else {
         window.cursor.icon = CursorIcon::Default;
@@ -317,13 +413,7 @@ fn propagate_movement(
     mut led_q: Query<(&mut LedArea, &MeshRef)>,
 ) {
     let (ui_camera, ui_camera_transform) = camera_q.single();
-    let RenderTarget::Window(window_ref) = ui_camera.target else {
-        panic!("Expected a window render target");
-    };
-    let window = match window_ref {
-        WindowRef::Primary => primary_window_q.single(),
-        WindowRef::Entity(window) => windows_q.get(window).unwrap(),
-    };
+    let window = ui_window(ui_camera, &windows_q, &primary_window_q);
 
     for (mut led, mesh_ref) in led_q.iter_mut() {
         let Ok((parent_transform, parent_aabb)) = meshes_q.get(mesh_ref.0) else {
@@ -377,13 +467,66 @@ fn propagate_movement(
     }
 }
 
+fn propagate_control_points(
+    camera_q: Query<(&Camera, &GlobalTransform), With<UiCamera>>,
+    windows_q: Query<&Window>,
+    primary_window_q: Query<&Window, With<PrimaryWindow>>,
+    control_points_q: Query<(&Transform, &ControlPoint), Changed<Transform>>,
+    mut paths_q: Query<&mut LedPath>,
+) {
+    let (ui_camera, ui_camera_transform) = camera_q.single();
+    let window = ui_window(ui_camera, &windows_q, &primary_window_q);
+
+    for (transform, control_point) in control_points_q.iter() {
+        let Ok(mut path) = paths_q.get_mut(control_point.area) else {
+            continue;
+        };
+        let Some(screen) = ui_camera.world_to_viewport(ui_camera_transform, transform.translation)
+        else {
+            continue;
+        };
+        let screen = screen * window.scale_factor();
+
+        // Only write back real movement, so spawning the handle doesn't resample the path
+        if path
+            .control_points
+            .get(control_point.index)
+            .is_some_and(|point| point.distance_squared(screen) > 0.01)
+        {
+            path.control_points[control_point.index] = screen;
+        }
+    }
+}
+
+/// The window the UI camera renders to.
+fn ui_window<'a>(
+    ui_camera: &Camera,
+    windows_q: &'a Query<&Window>,
+    primary_window_q: &'a Query<&Window, With<PrimaryWindow>>,
+) -> &'a Window {
+    let RenderTarget::Window(window_ref) = ui_camera.target else {
+        panic!("Expected a window render target");
+    };
+    match window_ref {
+        WindowRef::Primary => primary_window_q.single(),
+        WindowRef::Entity(window) => windows_q.get(window).unwrap(),
+    }
+}
+
 fn despawn_removed_areas(
     mut commands: Commands,
     mut removed_areas: RemovedComponents<LedArea>,
     area_refs: Query<(Entity, &AreaRef)>,
     corner_query: Query<(Entity, &DragCorner)>,
+    control_points_q: Query<(Entity, &ControlPoint)>,
 ) {
     for removed_area in removed_areas.read() {
+        for (control_point_entity, control_point) in control_points_q.iter() {
+            if control_point.area == removed_area {
+                commands.entity(control_point_entity).despawn_recursive();
+            }
+        }
+
         // Find the rectangle entity associated with the removed LedArea
         if let Some((rect_entity, _)) = area_refs
             .iter()
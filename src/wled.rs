@@ -0,0 +1,224 @@
+use crate::output::OutputData;
+use crate::socket::{bind_output_socket, bind_udp_socket, OutputSocket};
+use bevy::prelude::*;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+
+/// The UDP port WLED listens on for realtime data.
+pub const WLED_PORT: u16 = 21324;
+
+const WLED_WARLS: u8 = 1;
+const WLED_DRGB: u8 = 2;
+const WLED_DNRGB: u8 = 4;
+
+const WARLS_MAX_LEDS: usize = 255;
+const DRGB_MAX_LEDS: usize = 490;
+const DNRGB_MAX_LEDS: usize = 489;
+
+pub struct WledPlugin;
+
+impl Plugin for WledPlugin {
+    fn build(&self, app: &mut App) {
+        app.observe(send_wled_output).add_systems(
+            PreUpdate,
+            bind_output_socket::<WledSocket, WledOutput>.run_if(not(resource_exists::<WledSocket>)),
+        );
+    }
+}
+
+/// The socket used to send WLED realtime packets. Insert this resource to bind to a specific
+/// interface, otherwise an ephemeral port is bound on all interfaces once there is a
+/// `WledOutput`.
+#[derive(Resource, Deref)]
+pub struct WledSocket(UdpSocket);
+
+impl WledSocket {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        bind_udp_socket(addr).map(Self)
+    }
+}
+
+impl OutputSocket for WledSocket {
+    const PROTOCOL: &'static str = "WLED";
+
+    fn bind_default() -> io::Result<Self> {
+        Self::bind((Ipv4Addr::UNSPECIFIED, 0))
+    }
+}
+
+/// The WLED realtime UDP protocol to send with. Areas that WARLS or DRGB can't address are
+/// sent with DNRGB.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WledProtocol {
+    /// Index and colour for each LED, up to 255 LEDs.
+    Warls,
+    /// Colours for up to 490 LEDs starting at the first LED.
+    Drgb,
+    /// Colours starting at an index, split into packets of 489 LEDs for longer strips.
+    #[default]
+    Dnrgb,
+}
+
+/// Sends a `LedArea`'s pixel data to a WLED node using its realtime UDP protocols.
+#[derive(Component, Clone, Debug)]
+pub struct WledOutput {
+    pub destination: SocketAddr,
+    pub protocol: WledProtocol,
+    /// Seconds without data before WLED returns to its own effects, where 255 never returns.
+    pub timeout: u8,
+    /// The index of the node's LED that receives the area's first pixel.
+    pub start_index: u16,
+}
+
+impl WledOutput {
+    pub fn new(ip: impl Into<IpAddr>) -> Self {
+        Self {
+            destination: SocketAddr::new(ip.into(), WLED_PORT),
+            protocol: WledProtocol::default(),
+            timeout: 2,
+            start_index: 0,
+        }
+    }
+
+    pub fn protocol(mut self, protocol: WledProtocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: u8) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn start_index(mut self, start_index: u16) -> Self {
+        self.start_index = start_index;
+        self
+    }
+}
+
+fn send_wled_output(
+    trigger: Trigger<OutputData>,
+    outputs_q: Query<&WledOutput>,
+    socket: Option<Res<WledSocket>>,
+) {
+    let (Ok(output), Some(socket)) = (outputs_q.get(trigger.entity()), socket) else {
+        return;
+    };
+
+    let rgb = trigger.event().rgb();
+    for packet in wled_packets(output, &rgb) {
+        if let Err(err) = socket.send_to(&packet, output.destination) {
+            warn!(
+                "Failed to send WLED packet to {}: {err}",
+                output.destination
+            );
+        }
+    }
+}
+
+/// The packets for an area's RGB data. DNRGB stands in for WARLS and DRGB when they can't
+/// address every LED of the area.
+fn wled_packets(output: &WledOutput, rgb: &[u8]) -> Vec<Vec<u8>> {
+    let start = output.start_index as usize;
+    let count = rgb.len() / 3;
+    match output.protocol {
+        WledProtocol::Warls if start + count <= WARLS_MAX_LEDS => {
+            let mut packet = vec![WLED_WARLS, output.timeout];
+            for (i, pixel) in rgb.chunks_exact(3).enumerate() {
+                packet.push((start + i) as u8);
+                packet.extend_from_slice(pixel);
+            }
+            vec![packet]
+        }
+        WledProtocol::Drgb if start == 0 && count <= DRGB_MAX_LEDS => {
+            let mut packet = vec![WLED_DRGB, output.timeout];
+            packet.extend_from_slice(&rgb[..count * 3]);
+            vec![packet]
+        }
+        protocol => {
+            if protocol != WledProtocol::Dnrgb {
+                warn_once!("{protocol:?} can't address every LED of a WLED area, sending DNRGB");
+            }
+
+            let mut packets = Vec::new();
+            for (i, chunk) in rgb.chunks(DNRGB_MAX_LEDS * 3).enumerate() {
+                let Ok(index) = u16::try_from(start + i * DNRGB_MAX_LEDS) else {
+                    warn_once!("WLED can only address {} LEDs, truncating", 1 << 16);
+                    break;
+                };
+                let mut packet = vec![WLED_DNRGB, output.timeout];
+                packet.extend_from_slice(&index.to_be_bytes());
+                packet.extend_from_slice(chunk);
+                packets.push(packet);
+            }
+            packets
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(protocol: WledProtocol, start_index: u16) -> WledOutput {
+        WledOutput::new(Ipv4Addr::LOCALHOST)
+            .protocol(protocol)
+            .start_index(start_index)
+    }
+
+    fn leds(count: usize) -> Vec<u8> {
+        (0..count * 3).map(|i| i as u8).collect()
+    }
+
+    #[test]
+    fn warls_indexes_each_led() {
+        let packets = wled_packets(&output(WledProtocol::Warls, 3), &leds(2));
+        assert_eq!(packets, vec![vec![1, 2, 3, 0, 1, 2, 4, 3, 4, 5]]);
+    }
+
+    #[test]
+    fn drgb_sends_from_the_first_led() {
+        let packets = wled_packets(&output(WledProtocol::Drgb, 0), &leds(2));
+        assert_eq!(packets, vec![vec![2, 2, 0, 1, 2, 3, 4, 5]]);
+    }
+
+    #[test]
+    fn dnrgb_starts_at_the_start_index() {
+        let packets = wled_packets(&output(WledProtocol::Dnrgb, 10), &leds(2));
+        assert_eq!(packets, vec![vec![4, 2, 0, 10, 0, 1, 2, 3, 4, 5]]);
+    }
+
+    #[test]
+    fn dnrgb_splits_long_strips() {
+        let rgb = leds(DNRGB_MAX_LEDS + 1);
+        let packets = wled_packets(&output(WledProtocol::Dnrgb, 0), &rgb);
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0][..4], [4, 2, 0, 0]);
+        assert_eq!(packets[0].len(), 4 + DNRGB_MAX_LEDS * 3);
+        assert_eq!(packets[1][..4], [4, 2, 0x01, 0xe9]);
+        assert_eq!(packets[1][4..], rgb[DNRGB_MAX_LEDS * 3..]);
+    }
+
+    #[test]
+    fn falls_back_to_dnrgb_when_the_protocol_cant_address_the_leds() {
+        let packets = wled_packets(&output(WledProtocol::Drgb, 5), &leds(1));
+        assert_eq!(packets, vec![vec![4, 2, 0, 5, 0, 1, 2]]);
+
+        let packets = wled_packets(&output(WledProtocol::Drgb, 0), &leds(DRGB_MAX_LEDS + 1));
+        assert!(packets.iter().all(|packet| packet[0] == WLED_DNRGB));
+
+        let packets = wled_packets(&output(WledProtocol::Warls, 250), &leds(6));
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0][..4], [4, 2, 0, 250]);
+    }
+
+    #[test]
+    fn dnrgb_stops_at_the_last_index() {
+        let packets = wled_packets(
+            &output(WledProtocol::Dnrgb, u16::MAX),
+            &leds(DNRGB_MAX_LEDS + 1),
+        );
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0][..4], [4, 2, 0xff, 0xff]);
+    }
+}
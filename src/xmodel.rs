@@ -0,0 +1,833 @@
+use crate::output::DMX_UNIVERSE_SIZE;
+use crate::text_asset::{TextAsset, TextAssetLoader};
+use crate::{
+    ArtNetOutput, ColorCalibration, DdpOutput, Dimmer, EnttecOutput, FixtureType, LedArea,
+    LedBundle, LedLut, LedMatrix, LedPoints, LedTonemapping, MatrixCorner, MatrixWiring, OpcOutput,
+    OutputDepth, PixelChannel, PixelFormat, PowerModel, SacnOutput, TemporalDither, TransferCurve,
+    WhiteBalance, WhiteExtraction, WledOutput,
+};
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+pub struct XModelPlugin;
+
+impl Plugin for XModelPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<XModel>()
+            .init_asset_loader::<XModelLoader>()
+            .add_systems(PostUpdate, spawn_xmodel_strings)
+            .observe(despawn_xmodel_strings);
+    }
+}
+
+/// A model exported from xLights as an `.xmodel` file, reduced to the position of each node
+/// and the strings they're wired on.
+#[derive(Asset, TypePath, Clone, Debug, PartialEq)]
+pub struct XModel {
+    pub name: String,
+    /// The model's xLights type, e.g. `Custom`, `Horiz Matrix` or `Tree 360`.
+    pub display_as: String,
+    /// The position of each node within the model from 0 to 1, with y increasing downwards
+    /// like the screen, in node order.
+    pub nodes: Vec<Vec2>,
+    /// The nodes on each string.
+    pub strings: Vec<Range<usize>>,
+    /// The channel each string starts on, relative to the model's first channel.
+    pub string_offsets: Vec<usize>,
+    /// The channels sent for each node.
+    pub format: PixelFormat,
+}
+
+impl FromStr for XModel {
+    type Err = XModelError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let document = roxmltree::Document::parse(s)?;
+        let root = document.root_element();
+
+        let display_as = match root.attribute("DisplayAs") {
+            Some(display_as) => display_as.to_string(),
+            None if root.has_tag_name("custommodel") => "Custom".to_string(),
+            None => return Err(XModelError::MissingAttribute("DisplayAs".to_string())),
+        };
+        let strings = parse_attribute::<usize>(root, "parm1", 1)?.max(1);
+        let nodes_per_string = parse_attribute::<usize>(root, "parm2", 1)?;
+        let bottom = root.attribute("StartSide").unwrap_or("B") == "B";
+        let reversed = root.attribute("Dir").unwrap_or("L") == "R";
+
+        let (nodes, strings) = match display_as.as_str() {
+            "Custom" => {
+                let strings = parse_attribute::<usize>(root, "CustomStrings", 1)?.max(1);
+                (custom_nodes(root)?, strings)
+            }
+            "Single Line" => {
+                let count = strings * nodes_per_string;
+                let nodes = (0..count)
+                    .map(|i| Vec2::new((i as f32 + 0.5) / count as f32, 0.5))
+                    .collect();
+                (mirrored(nodes, reversed), strings)
+            }
+            "Arches" => {
+                let arc = parse_attribute::<f32>(root, "arc", 180.0)?;
+                let nodes = arch_nodes(strings, nodes_per_string, arc);
+                (mirrored(nodes, reversed), strings)
+            }
+            "Horiz Matrix" | "Vert Matrix" => {
+                let matrix = matrix_layout(root, &display_as, strings, nodes_per_string)?
+                    .start(start_corner(bottom, reversed));
+                (matrix_nodes(&matrix), strings)
+            }
+            tree if tree.starts_with("Tree") => {
+                let matrix = matrix_layout(root, "Vert Matrix", strings, nodes_per_string)?
+                    .start(start_corner(bottom, reversed));
+                // Fan the columns out from the top, so the tree widens towards its base
+                let nodes = matrix_nodes(&matrix)
+                    .into_iter()
+                    .map(|p| Vec2::new(0.5 + (p.x - 0.5) * p.y, p.y))
+                    .collect();
+                (nodes, strings)
+            }
+            _ => return Err(XModelError::Unsupported(display_as)),
+        };
+
+        let format = string_format(root.attribute("StringType").unwrap_or("RGB Nodes"));
+        let per_string = nodes.len().div_ceil(strings).max(1);
+        let strings = (0..strings)
+            .map(|i| (i * per_string).min(nodes.len())..((i + 1) * per_string).min(nodes.len()))
+            .collect::<Vec<_>>();
+        let string_offsets = string_offsets(root, &strings, format.channels())?;
+
+        Ok(Self {
+            name: root.attribute("name").unwrap_or_default().to_string(),
+            display_as,
+            nodes,
+            strings,
+            string_offsets,
+            format,
+        })
+    }
+}
+
+fn parse_attribute<T: FromStr>(
+    node: roxmltree::Node,
+    name: &str,
+    default: T,
+) -> Result<T, XModelError> {
+    match node.attribute(name) {
+        Some(value) => value
+            .trim()
+            .parse()
+            .map_err(|_| XModelError::InvalidAttribute(name.to_string())),
+        None => Ok(default),
+    }
+}
+
+/// The nodes of a custom model, from either its grid of node numbers or the compressed
+/// `node,row,column[,layer]` list.
+fn custom_nodes(root: roxmltree::Node) -> Result<Vec<Vec2>, XModelError> {
+    let mut cells = Vec::new();
+    let attribute = if root.has_attribute("CustomModelCompressed") {
+        "CustomModelCompressed"
+    } else {
+        "CustomModel"
+    };
+    if let Some(compressed) = root.attribute("CustomModelCompressed") {
+        for entry in compressed
+            .split(';')
+            .filter(|entry| !entry.trim().is_empty())
+        {
+            let fields = entry
+                .split(',')
+                .map(|field| field.trim().parse::<usize>().ok())
+                .collect::<Option<Vec<_>>>();
+            match fields.as_deref() {
+                Some([node, row, column, ..]) => cells.push((*node, *row, *column)),
+                _ => return Err(XModelError::InvalidAttribute(attribute.to_string())),
+            }
+        }
+    } else {
+        let Some(grid) = root.attribute("CustomModel") else {
+            return Err(XModelError::MissingAttribute("CustomModel".to_string()));
+        };
+        for layer in grid.split('|') {
+            for (row, line) in layer.split(';').enumerate() {
+                for (column, cell) in line.split(',').enumerate() {
+                    let cell = cell.trim();
+                    if cell.is_empty() {
+                        continue;
+                    }
+                    let node = cell
+                        .parse()
+                        .map_err(|_| XModelError::InvalidAttribute(attribute.to_string()))?;
+                    cells.push((node, row, column));
+                }
+            }
+        }
+    }
+
+    // The grid may be larger than its nodes, but never smaller
+    let width = cells
+        .iter()
+        .map(|(_, _, column)| column + 1)
+        .max()
+        .unwrap_or(1);
+    let height = cells.iter().map(|(_, row, _)| row + 1).max().unwrap_or(1);
+    let width = parse_attribute(root, "parm1", width)?.max(width);
+    let height = parse_attribute(root, "parm2", height)?.max(height);
+
+    // Node numbers are 1-based, and a node may span several cells. There can't be more nodes
+    // than cells, so don't trust a larger number to size the model
+    let count = cells.iter().map(|(node, _, _)| *node).max().unwrap_or(0);
+    if count > cells.len() {
+        return Err(XModelError::InvalidAttribute(attribute.to_string()));
+    }
+    let mut nodes = vec![None; count];
+    for (node, row, column) in cells {
+        if node == 0 || nodes[node - 1].is_some() {
+            continue;
+        }
+        nodes[node - 1] = Some(Vec2::new(
+            (column as f32 + 0.5) / width as f32,
+            (row as f32 + 0.5) / height as f32,
+        ));
+    }
+
+    let missing = nodes.iter().filter(|node| node.is_none()).count();
+    if missing > 0 {
+        warn!("{missing} nodes of custom model aren't placed, centring them");
+    }
+    Ok(nodes
+        .into_iter()
+        .map(|node| node.unwrap_or(Vec2::splat(0.5)))
+        .collect())
+}
+
+/// A semicircle of nodes in each of `arches` side by side cells, running left to right.
+fn arch_nodes(arches: usize, nodes_per_arch: usize, arc: f32) -> Vec<Vec2> {
+    let arc = arc.to_radians();
+    let radius = Vec2::new(0.5 / arches as f32, 1.0);
+    (0..arches)
+        .flat_map(|arch| {
+            let center = Vec2::new((arch as f32 + 0.5) / arches as f32, 1.0);
+            (0..nodes_per_arch).map(move |i| {
+                let t = (i as f32 + 0.5) / nodes_per_arch as f32;
+                let angle = std::f32::consts::FRAC_PI_2 + arc / 2.0 - t * arc;
+                center + Vec2::new(angle.cos(), -angle.sin()) * radius
+            })
+        })
+        .collect()
+}
+
+/// The wiring of a matrix model, where each string is folded into `StrandsPerString` strands.
+fn matrix_layout(
+    root: roxmltree::Node,
+    display_as: &str,
+    strings: usize,
+    nodes_per_string: usize,
+) -> Result<LedMatrix, XModelError> {
+    let strands = parse_attribute::<usize>(root, "parm3", 1)?.max(1);
+    let lines = (strings * strands) as u32;
+    let nodes_per_strand = (nodes_per_string / strands) as u32;
+    Ok(if display_as == "Horiz Matrix" {
+        LedMatrix::new(nodes_per_strand, lines).wiring(MatrixWiring::SerpentineRows)
+    } else {
+        LedMatrix::new(lines, nodes_per_strand).wiring(MatrixWiring::SerpentineColumns)
+    })
+}
+
+fn start_corner(bottom: bool, reversed: bool) -> MatrixCorner {
+    match (bottom, reversed) {
+        (false, false) => MatrixCorner::TopLeft,
+        (false, true) => MatrixCorner::TopRight,
+        (true, false) => MatrixCorner::BottomLeft,
+        (true, true) => MatrixCorner::BottomRight,
+    }
+}
+
+fn matrix_nodes(matrix: &LedMatrix) -> Vec<Vec2> {
+    let size = Vec2::new(matrix.columns.max(1) as f32, matrix.rows.max(1) as f32);
+    (0..matrix.count())
+        .map(|index| (matrix.position(index).as_vec2() + 0.5) / size)
+        .collect()
+}
+
+fn mirrored(nodes: Vec<Vec2>, mirror: bool) -> Vec<Vec2> {
+    if !mirror {
+        return nodes;
+    }
+    nodes
+        .into_iter()
+        .map(|p| Vec2::new(1.0 - p.x, p.y))
+        .collect()
+}
+
+fn string_format(string_type: &str) -> PixelFormat {
+    use PixelChannel::*;
+    match string_type {
+        "RGB Nodes" => PixelFormat::RGB,
+        "GRB Nodes" => PixelFormat::GRB,
+        "BGR Nodes" => PixelFormat::BGR,
+        "RBG Nodes" => PixelFormat(&[Red, Blue, Green]),
+        "BRG Nodes" => PixelFormat(&[Blue, Red, Green]),
+        "GBR Nodes" => PixelFormat(&[Green, Blue, Red]),
+        "RGBW Nodes" => PixelFormat::RGBW,
+        single if single.contains("Single Color") || single == "Strobes" => PixelFormat::SINGLE,
+        other => {
+            warn!("Unsupported xLights string type {other}, sending RGB");
+            PixelFormat::RGB
+        }
+    }
+}
+
+/// The channel each string starts on relative to the first. Strings run on from each other
+/// unless the model gives each one its own start channel.
+fn string_offsets(
+    root: roxmltree::Node,
+    strings: &[Range<usize>],
+    channels_per_node: usize,
+) -> Result<Vec<usize>, XModelError> {
+    let contiguous = strings
+        .iter()
+        .map(|string| string.start * channels_per_node)
+        .collect();
+    if root.attribute("Advanced") != Some("1") {
+        return Ok(contiguous);
+    }
+
+    let starts = (1..=strings.len())
+        .map(|i| {
+            root.attribute(format!("String{i}").as_str())
+                .and_then(|start| start.trim().parse::<usize>().ok())
+        })
+        .collect::<Option<Vec<_>>>();
+    match starts {
+        Some(starts) if starts.iter().all(|start| *start >= starts[0]) => {
+            Ok(starts.iter().map(|start| start - starts[0]).collect())
+        }
+        // Start channels like `>Model:1` or `!Controller:1` depend on the rest of the layout
+        _ => {
+            warn!("Only numeric string start channels are supported, running strings on");
+            Ok(contiguous)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum XModelError {
+    Xml(roxmltree::Error),
+    MissingAttribute(String),
+    InvalidAttribute(String),
+    /// A model type that can't be laid out, by its `DisplayAs` attribute.
+    Unsupported(String),
+}
+
+impl fmt::Display for XModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            XModelError::Xml(err) => write!(f, "invalid xmodel xml: {err}"),
+            XModelError::MissingAttribute(name) => {
+                write!(f, "xmodel is missing the {name} attribute")
+            }
+            XModelError::InvalidAttribute(name) => {
+                write!(f, "invalid value for xmodel attribute {name}")
+            }
+            XModelError::Unsupported(display_as) => {
+                write!(f, "unsupported xmodel type {display_as}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for XModelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            XModelError::Xml(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<roxmltree::Error> for XModelError {
+    fn from(err: roxmltree::Error) -> Self {
+        XModelError::Xml(err)
+    }
+}
+
+impl TextAsset for XModel {
+    const EXTENSIONS: &'static [&'static str] = &["xmodel"];
+}
+
+/// Loads `.xmodel` files as `XModel` assets.
+pub type XModelLoader = TextAssetLoader<XModel>;
+
+/// Places an `XModel` on screen, spawning a `LedArea` for each of its strings once it has
+/// loaded. Outputs on the same entity are templates for the strings, which each get a copy
+/// moved on to the string's start channel, or the matching pixel for OPC and WLED. The
+/// components that control how pixels are encoded, such as `Dimmer` and `TransferCurve`, are
+/// copied onto the strings as they are spawned, with a `PixelFormat` overriding the model's.
+/// Change them on the strings afterwards, or respawn the strings by touching the `LedXModel`.
+#[derive(Component, Clone, Debug)]
+pub struct LedXModel {
+    pub model: Handle<XModel>,
+    /// The top left corner of the model, in screen pixels.
+    pub position: Vec2,
+    /// The size the model is stretched to, in screen pixels.
+    pub size: Vec2,
+    /// The radius sampled around each node, in screen pixels.
+    pub radius: f32,
+    pub num_samples: u32,
+}
+
+impl LedXModel {
+    pub fn new(model: Handle<XModel>) -> Self {
+        Self {
+            model,
+            position: Vec2::ZERO,
+            size: Vec2::splat(100.0),
+            radius: 5.0,
+            num_samples: 10,
+        }
+    }
+
+    pub fn position(mut self, position: Vec2) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn size(mut self, size: Vec2) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    pub fn samples(mut self, num_samples: u32) -> Self {
+        self.num_samples = num_samples;
+        self
+    }
+}
+
+/// One string of a `LedXModel`.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct XModelString {
+    /// The entity with the `LedXModel`.
+    pub model: Entity,
+    pub index: usize,
+    /// The channel of the string's first node, relative to the model's first channel.
+    pub channel_offset: usize,
+}
+
+/// The string entities spawned for a `LedXModel`.
+#[derive(Component, Default)]
+struct XModelStrings(Vec<Entity>);
+
+type XModelPixelSettings<'a> = (
+    Option<&'a PixelFormat>,
+    Option<&'a LedTonemapping>,
+    Option<&'a LedLut>,
+    Option<&'a WhiteExtraction>,
+    Option<&'a WhiteBalance>,
+    Option<&'a TransferCurve>,
+    Option<&'a OutputDepth>,
+    Option<&'a TemporalDither>,
+    Option<&'a Dimmer>,
+    Option<&'a ColorCalibration>,
+    Option<&'a FixtureType>,
+    Option<&'a PowerModel>,
+);
+
+type XModelOutputs<'a> = (
+    Option<&'a ArtNetOutput>,
+    Option<&'a SacnOutput>,
+    Option<&'a DdpOutput>,
+    Option<&'a EnttecOutput>,
+    Option<&'a OpcOutput>,
+    Option<&'a WledOutput>,
+);
+
+fn spawn_xmodel_strings(
+    mut commands: Commands,
+    mut asset_events: EventReader<AssetEvent<XModel>>,
+    xmodels: Res<Assets<XModel>>,
+    models_q: Query<(
+        Entity,
+        Ref<LedXModel>,
+        Option<&XModelStrings>,
+        XModelPixelSettings,
+        XModelOutputs,
+    )>,
+) {
+    let loaded = asset_events
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(*id),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    for (entity, led_xmodel, strings, settings, outputs) in models_q.iter() {
+        if !led_xmodel.is_changed() && !loaded.contains(&led_xmodel.model.id()) {
+            continue;
+        }
+        let Some(xmodel) = xmodels.get(&led_xmodel.model) else {
+            continue;
+        };
+        let (
+            format,
+            tonemapping,
+            lut,
+            white_extraction,
+            white_balance,
+            transfer_curve,
+            depth,
+            dither,
+            dimmer,
+            calibration,
+            fixture_type,
+            power_model,
+        ) = settings;
+        let (artnet, sacn, ddp, enttec, opc, wled) = outputs;
+
+        let format = format.copied().unwrap_or(xmodel.format);
+        if format.channels() != xmodel.format.channels() {
+            warn!(
+                "The PixelFormat of {entity} has a different channel count to its xmodel, whose \
+                 string start channels assume the xmodel's"
+            );
+        }
+
+        for string in strings.into_iter().flat_map(|strings| &strings.0) {
+            if let Some(mut string) = commands.get_entity(*string) {
+                string.despawn();
+            }
+        }
+
+        let mut spawned = Vec::with_capacity(xmodel.strings.len());
+        for (index, (nodes, offset)) in xmodel
+            .strings
+            .iter()
+            .zip(xmodel.string_offsets.iter().copied())
+            .enumerate()
+        {
+            let points = xmodel.nodes[nodes.clone()]
+                .iter()
+                .map(|node| led_xmodel.position + *node * led_xmodel.size)
+                .collect::<Vec<_>>();
+            let mut string = commands.spawn((
+                LedBundle {
+                    area: LedArea {
+                        count: points.len() as u32,
+                        position: led_xmodel.position,
+                        size: led_xmodel.size,
+                        num_samples: led_xmodel.num_samples,
+                        ..default()
+                    },
+                    ..default()
+                },
+                RenderLayers::layer(32),
+                LedPoints {
+                    points,
+                    radius: led_xmodel.radius,
+                },
+                format,
+                XModelString {
+                    model: entity,
+                    index,
+                    channel_offset: offset,
+                },
+            ));
+            insert_copy(&mut string, tonemapping);
+            insert_copy(&mut string, lut);
+            insert_copy(&mut string, white_extraction);
+            insert_copy(&mut string, white_balance);
+            insert_copy(&mut string, transfer_curve);
+            insert_copy(&mut string, depth);
+            insert_copy(&mut string, dither);
+            insert_copy(&mut string, dimmer);
+            insert_copy(&mut string, calibration);
+            insert_copy(&mut string, fixture_type);
+            insert_copy(&mut string, power_model);
+
+            if let Some(output) = artnet {
+                let (universes, patch) = output.patch.advance(offset);
+                let port_address = output.port_address().saturating_add(universes);
+                string.insert(ArtNetOutput {
+                    net: ((port_address >> 8) & 0x7f) as u8,
+                    subnet: ((port_address >> 4) & 0xf) as u8,
+                    universe: (port_address & 0xf) as u8,
+                    patch,
+                    ..output.clone()
+                });
+            }
+            if let Some(output) = sacn {
+                let (universes, patch) = output.patch.advance(offset);
+                string.insert(SacnOutput {
+                    universe: output.universe.saturating_add(universes),
+                    patch,
+                    ..output.clone()
+                });
+            }
+            if let Some(output) = ddp {
+                string.insert(DdpOutput {
+                    offset: output.offset + offset,
+                    ..output.clone()
+                });
+            }
+            if let Some(output) = enttec {
+                let start_channel = u16::try_from(offset)
+                    .ok()
+                    .and_then(|offset| output.start_channel.checked_add(offset))
+                    .filter(|channel| *channel as usize <= DMX_UNIVERSE_SIZE);
+                match start_channel {
+                    Some(start_channel) => {
+                        string.insert(EnttecOutput {
+                            start_channel,
+                            ..output.clone()
+                        });
+                    }
+                    None => warn!("String {index} of {entity} starts beyond the Enttec universe"),
+                }
+            }
+            // OPC and WLED address pixels rather than channels
+            let nodes = offset / format.channels().max(1);
+            if let Some(output) = opc {
+                string.insert(OpcOutput {
+                    offset: output.offset + nodes,
+                    ..output.clone()
+                });
+            }
+            if let Some(output) = wled {
+                let start_index = u16::try_from(nodes)
+                    .ok()
+                    .and_then(|nodes| output.start_index.checked_add(nodes));
+                match start_index {
+                    Some(start_index) => {
+                        string.insert(WledOutput {
+                            start_index,
+                            ..output.clone()
+                        });
+                    }
+                    None => warn!("String {index} of {entity} starts beyond the last WLED LED"),
+                }
+            }
+            spawned.push(string.id());
+        }
+        commands.entity(entity).insert(XModelStrings(spawned));
+    }
+}
+
+fn insert_copy<C: Component + Clone>(string: &mut EntityCommands, component: Option<&C>) {
+    if let Some(component) = component {
+        string.insert(component.clone());
+    }
+}
+
+fn despawn_xmodel_strings(
+    trigger: Trigger<OnRemove, LedXModel>,
+    mut commands: Commands,
+    strings_q: Query<&XModelStrings>,
+) {
+    let Ok(strings) = strings_q.get(trigger.entity()) else {
+        return;
+    };
+    for string in &strings.0 {
+        if let Some(mut string) = commands.get_entity(*string) {
+            string.despawn();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(xml: &str) -> XModel {
+        xml.parse().expect("fixture should parse")
+    }
+
+    fn assert_nodes(actual: &[Vec2], expected: &[Vec2]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(a.abs_diff_eq(*e, 1e-5), "{actual:?} != {expected:?}");
+        }
+    }
+
+    #[test]
+    fn custom_grid() {
+        let model = parse(
+            r#"<custommodel name="Star" parm1="3" parm2="2" StringType="RGB Nodes"
+                CustomModel="1,,2;,3," />"#,
+        );
+        assert_eq!(model.name, "Star");
+        assert_eq!(model.display_as, "Custom");
+        assert_nodes(
+            &model.nodes,
+            &[
+                Vec2::new(0.5 / 3.0, 0.25),
+                Vec2::new(2.5 / 3.0, 0.25),
+                Vec2::new(1.5 / 3.0, 0.75),
+            ],
+        );
+        assert_eq!(model.strings, vec![0..3]);
+        assert_eq!(model.string_offsets, vec![0]);
+        assert_eq!(model.format, PixelFormat::RGB);
+    }
+
+    #[test]
+    fn custom_compressed_with_strings() {
+        let model = parse(
+            r#"<custommodel name="Square" parm1="2" parm2="2" CustomStrings="2"
+                CustomModelCompressed="2,0,1;1,1,0;3,1,1;" />"#,
+        );
+        assert_nodes(
+            &model.nodes,
+            &[
+                Vec2::new(0.25, 0.75),
+                Vec2::new(0.75, 0.25),
+                Vec2::new(0.75, 0.75),
+            ],
+        );
+        assert_eq!(model.strings, vec![0..2, 2..3]);
+        assert_eq!(model.string_offsets, vec![0, 6]);
+    }
+
+    #[test]
+    fn custom_nodes_beyond_the_cells_are_rejected() {
+        let result = r#"<custommodel CustomModel="1,1000000000" />"#.parse::<XModel>();
+        assert!(
+            matches!(result, Err(XModelError::InvalidAttribute(name)) if name == "CustomModel")
+        );
+    }
+
+    #[test]
+    fn horizontal_matrix_from_the_top_left() {
+        let model = parse(
+            r#"<matrixmodel DisplayAs="Horiz Matrix" parm1="2" parm2="3" parm3="1"
+                StartSide="T" Dir="L" />"#,
+        );
+        let cell = |column: f32, row: f32| Vec2::new((column + 0.5) / 3.0, (row + 0.5) / 2.0);
+        assert_nodes(
+            &model.nodes,
+            &[
+                cell(0.0, 0.0),
+                cell(1.0, 0.0),
+                cell(2.0, 0.0),
+                cell(2.0, 1.0),
+                cell(1.0, 1.0),
+                cell(0.0, 1.0),
+            ],
+        );
+        assert_eq!(model.strings, vec![0..3, 3..6]);
+        assert_eq!(model.string_offsets, vec![0, 9]);
+    }
+
+    #[test]
+    fn vertical_matrix_from_the_bottom_left() {
+        let model = parse(
+            r#"<matrixmodel DisplayAs="Vert Matrix" parm1="2" parm2="2" StartSide="B" Dir="L" />"#,
+        );
+        assert_nodes(
+            &model.nodes,
+            &[
+                Vec2::new(0.25, 0.75),
+                Vec2::new(0.25, 0.25),
+                Vec2::new(0.75, 0.25),
+                Vec2::new(0.75, 0.75),
+            ],
+        );
+        assert_eq!(model.strings, vec![0..2, 2..4]);
+    }
+
+    #[test]
+    fn single_line_reversed() {
+        let model = parse(r#"<model DisplayAs="Single Line" parm1="1" parm2="4" Dir="R" />"#);
+        assert_nodes(
+            &model.nodes,
+            &[
+                Vec2::new(0.875, 0.5),
+                Vec2::new(0.625, 0.5),
+                Vec2::new(0.375, 0.5),
+                Vec2::new(0.125, 0.5),
+            ],
+        );
+        assert_eq!(model.strings, vec![0..4]);
+    }
+
+    #[test]
+    fn arches() {
+        let model = parse(r#"<model DisplayAs="Arches" parm1="2" parm2="3" />"#);
+        let x = 3f32.sqrt() / 2.0 * 0.25;
+        assert_nodes(
+            &model.nodes,
+            &[
+                Vec2::new(0.25 - x, 0.5),
+                Vec2::new(0.25, 0.0),
+                Vec2::new(0.25 + x, 0.5),
+                Vec2::new(0.75 - x, 0.5),
+                Vec2::new(0.75, 0.0),
+                Vec2::new(0.75 + x, 0.5),
+            ],
+        );
+        assert_eq!(model.strings, vec![0..3, 3..6]);
+    }
+
+    #[test]
+    fn tree_widens_towards_its_base() {
+        let model = parse(r#"<model DisplayAs="Tree 360" parm1="2" parm2="2" />"#);
+        assert_nodes(
+            &model.nodes,
+            &[
+                Vec2::new(0.3125, 0.75),
+                Vec2::new(0.4375, 0.25),
+                Vec2::new(0.5625, 0.25),
+                Vec2::new(0.6875, 0.75),
+            ],
+        );
+    }
+
+    #[test]
+    fn rgbw_strings_run_on() {
+        let model = parse(
+            r#"<model DisplayAs="Horiz Matrix" parm1="2" parm2="3" StringType="RGBW Nodes" />"#,
+        );
+        assert_eq!(model.format, PixelFormat::RGBW);
+        assert_eq!(model.string_offsets, vec![0, 12]);
+    }
+
+    #[test]
+    fn advanced_start_channels() {
+        let model = parse(
+            r#"<model DisplayAs="Horiz Matrix" parm1="2" parm2="3" Advanced="1"
+                String1="1" String2="100" />"#,
+        );
+        assert_eq!(model.string_offsets, vec![0, 99]);
+    }
+
+    #[test]
+    fn advanced_start_channels_that_reference_models_run_on() {
+        let model = parse(
+            r#"<model DisplayAs="Horiz Matrix" parm1="2" parm2="3" Advanced="1"
+                String1="1" String2=">Other:1" />"#,
+        );
+        assert_eq!(model.string_offsets, vec![0, 9]);
+    }
+
+    #[test]
+    fn unsupported_and_invalid_models() {
+        let result = r#"<model DisplayAs="Candy Canes" />"#.parse::<XModel>();
+        assert!(matches!(result, Err(XModelError::Unsupported(name)) if name == "Candy Canes"));
+        let result = r#"<model DisplayAs="Arches" parm1="two" />"#.parse::<XModel>();
+        assert!(matches!(result, Err(XModelError::InvalidAttribute(name)) if name == "parm1"));
+        assert!(matches!(
+            "<model".parse::<XModel>(),
+            Err(XModelError::Xml(_))
+        ));
+    }
+}